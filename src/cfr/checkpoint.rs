@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::serialization;
+use crate::utils::temp_file::TempFile;
+
+use super::node::StateNode;
+use super::state::{Game, GameState};
+
+/// Bytes every checkpoint file starts with
+const MAGIC: &[u8; 8] = b"CFRCKPT\0";
+/// Version of the checkpoint format. Bump this whenever the layout of the metadata or the
+/// strategies changes
+pub const VERSION: u32 = 6;
+
+/// Types that strategies can be stored as in a checkpoint. Each one has a tag that does not
+/// depend on the compiler version, so checkpoints can be checked against the types they are
+/// loaded as
+pub trait TypeTag {
+    /// Name of the type recorded in the checkpoint metadata
+    const TAG: &'static str;
+}
+
+impl TypeTag for f32 {
+    const TAG: &'static str = "f32";
+}
+
+impl TypeTag for f64 {
+    const TAG: &'static str = "f64";
+}
+
+impl TypeTag for String {
+    const TAG: &'static str = "string";
+}
+
+impl TypeTag for u64 {
+    const TAG: &'static str = "u64";
+}
+
+impl TypeTag for u128 {
+    const TAG: &'static str = "u128";
+}
+
+/// Describes the training run that produced a checkpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointMetadata {
+    /// Name of the game the strategies were trained on
+    pub game: String,
+    /// Parameters the game was created with
+    pub game_params: String,
+    /// Name of the solver used to train the strategies
+    pub solver: String,
+    /// Hyperparameters of the solver
+    pub solver_params: String,
+    /// Number of training rounds completed when the checkpoint was taken
+    pub iteration: usize,
+    /// Seed of the random number generator used for training, if it is known
+    pub seed: Option<u64>,
+    /// Floating point type of the strategies (i.e. f32)
+    pub float_type: String,
+    /// Type of the keys the strategies are stored under (i.e. u128 for scrabble)
+    pub key_type: String,
+    /// Wall-clock time spent training, in seconds
+    pub training_secs: f64,
+    /// Exploitability of the average strategy at the last evaluation before the checkpoint was
+    /// taken, if training was evaluated
+    pub exploitability: Option<f64>,
+    /// Time the checkpoint was saved, in seconds since the unix epoch
+    pub saved_at: u64,
+}
+
+impl CheckpointMetadata {
+    /// Creates the metadata for a checkpoint of the provided game, stamped with the current time
+    pub fn new<G, A>(game: &G, solver: String, solver_params: String) -> Self
+    where
+        G: Game,
+        A: TypeTag,
+        <G::State as GameState>::Key: TypeTag,
+    {
+        Self {
+            game: game.name(),
+            game_params: game.params(),
+            solver,
+            solver_params,
+            iteration: 0,
+            seed: None,
+            float_type: A::TAG.to_string(),
+            key_type: <G::State as GameState>::Key::TAG.to_string(),
+            training_secs: 0.0,
+            exploitability: None,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Reasons a checkpoint could not be saved or loaded
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file does not start with the checkpoint magic bytes
+    NotACheckpoint,
+    /// The file was written with a different version of the checkpoint format
+    UnsupportedVersion { found: u32, expected: u32 },
+    /// The checkpoint was trained with something other than what it is being loaded into
+    Mismatch {
+        field: &'static str,
+        expected: String,
+        found: String,
+    },
+    /// The metadata or strategies could not be decoded
+    Corrupt(bincode::Error),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "Failed to access checkpoint: {}", e),
+            CheckpointError::NotACheckpoint => write!(f, "File is not a checkpoint"),
+            CheckpointError::UnsupportedVersion { found, expected } => write!(
+                f,
+                "Checkpoint has format version {} but version {} is expected",
+                found, expected
+            ),
+            CheckpointError::Mismatch {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Checkpoint {} is '{}' but '{}' is expected",
+                field, found, expected
+            ),
+            CheckpointError::Corrupt(e) => write!(f, "Checkpoint is corrupt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> Self {
+        CheckpointError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for CheckpointError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(e) => CheckpointError::Io(e),
+            _ => CheckpointError::Corrupt(e),
+        }
+    }
+}
+
+/// Snapshot of a training run that contains everything needed to resume it
+pub struct Checkpoint<K, A> {
+    /// Description of the training run
+    pub metadata: CheckpointMetadata,
+    /// Cumulative utility of each player over all completed rounds
+    pub cumulative_utility: Vec<A>,
+    /// Strategies learned for each state
+    pub strategies: HashMap<K, StateNode<A>>,
+}
+
+/// Body of the checkpoint that follows the metadata
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "K: Hash + Eq + DeserializeOwned, A: DeserializeOwned"))]
+struct CheckpointBody<K, A> {
+    cumulative_utility: Vec<A>,
+    strategies: HashMap<K, StateNode<A>>,
+}
+
+/// Borrowed version of the body so the strategies do not have to be copied to save them.
+/// Serializes to the exact same bytes as `CheckpointBody`
+#[derive(Serialize)]
+struct CheckpointBodyRef<'a, K: Hash + Eq, A> {
+    cumulative_utility: &'a [A],
+    strategies: &'a HashMap<K, StateNode<A>>,
+}
+
+impl<K, A> Checkpoint<K, A>
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + TypeTag,
+    A: Serialize + DeserializeOwned + TypeTag,
+{
+    /// Saves the state of a training run to disk. The file starts with a header containing the
+    /// format version and metadata, followed by the strategies. The checkpoint is written to a
+    /// temporary file that is renamed once it is complete, so a crash while saving never leaves a
+    /// partially written checkpoint behind. The temporary file is removed if saving fails
+    pub fn save<P: AsRef<Path>>(
+        path: P,
+        metadata: &CheckpointMetadata,
+        cumulative_utility: &[A],
+        strategies: &HashMap<K, StateNode<A>>,
+    ) -> Result<(), CheckpointError> {
+        let tmp_file = TempFile::for_path(&path);
+        Self::write(tmp_file.path(), metadata, cumulative_utility, strategies)?;
+        tmp_file.persist(path)?;
+        Ok(())
+    }
+
+    fn write<P: AsRef<Path>>(
+        path: P,
+        metadata: &CheckpointMetadata,
+        cumulative_utility: &[A],
+        strategies: &HashMap<K, StateNode<A>>,
+    ) -> Result<(), CheckpointError> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(&file);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        serialization::serialize_into(&mut writer, metadata)?;
+        let body = CheckpointBodyRef {
+            cumulative_utility,
+            strategies,
+        };
+        serialization::serialize_into(&mut writer, &body)?;
+        writer.flush()?;
+        drop(writer);
+        // Make sure the contents are on disk before the rename makes them visible
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Loads a checkpoint, checking that it was written with the current format version and
+    /// with the same floating point and key types before decoding the strategies
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CheckpointError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let metadata = read_header(&mut reader)?;
+        check_field("float type", A::TAG, &metadata.float_type)?;
+        check_field("key type", K::TAG, &metadata.key_type)?;
+
+        let body: CheckpointBody<K, A> = serialization::deserialize_from(&mut reader)?;
+        Ok(Self {
+            metadata,
+            cumulative_utility: body.cumulative_utility,
+            strategies: body.strategies,
+        })
+    }
+
+    /// Loads a checkpoint and checks that it was trained on the provided game with the same
+    /// parameters
+    pub fn load_for_game<G: Game, P: AsRef<Path>>(
+        path: P,
+        game: &G,
+    ) -> Result<Self, CheckpointError> {
+        let checkpoint = Self::load(path)?;
+        check_field("game", &game.name(), &checkpoint.metadata.game)?;
+        check_field(
+            "game parameters",
+            &game.params(),
+            &checkpoint.metadata.game_params,
+        )?;
+        Ok(checkpoint)
+    }
+
+    /// Loads a checkpoint and checks that it was trained on the provided game by the same
+    /// solver with the same hyperparameters, so training can be resumed from it
+    pub fn load_for_solver<G: Game, P: AsRef<Path>>(
+        path: P,
+        game: &G,
+        solver: &str,
+        solver_params: &str,
+    ) -> Result<Self, CheckpointError> {
+        let checkpoint = Self::load_for_game(path, game)?;
+        check_field("solver", solver, &checkpoint.metadata.solver)?;
+        check_field(
+            "solver parameters",
+            solver_params,
+            &checkpoint.metadata.solver_params,
+        )?;
+        Ok(checkpoint)
+    }
+}
+
+/// Reads only the metadata of a checkpoint without decoding the strategies
+pub fn read_metadata<P: AsRef<Path>>(path: P) -> Result<CheckpointMetadata, CheckpointError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_header(&mut reader)
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<CheckpointMetadata, CheckpointError> {
+    let mut magic = [0; 8];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| CheckpointError::NotACheckpoint)?;
+    if &magic != MAGIC {
+        return Err(CheckpointError::NotACheckpoint);
+    }
+
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(CheckpointError::UnsupportedVersion {
+            found: version,
+            expected: VERSION,
+        });
+    }
+    Ok(serialization::deserialize_from(reader)?)
+}
+
+/// Checks that a field of the checkpoint metadata has the expected value
+pub fn check_field(
+    field: &'static str,
+    expected: &str,
+    found: &str,
+) -> Result<(), CheckpointError> {
+    if expected != found {
+        return Err(CheckpointError::Mismatch {
+            field,
+            expected: expected.to_string(),
+            found: found.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cfr::node::StateNode;
+    use crate::kuhn::KuhnPoker;
+    use crate::leduc::LeducHoldem;
+
+    use super::{read_metadata, Checkpoint, CheckpointError, CheckpointMetadata};
+
+    fn save_test_checkpoint(name: &str) -> std::path::PathBuf {
+        let mut node = StateNode::<f32>::new(2);
+        node.update_strategy_sum(1, 3.0);
+        node.update_baseline(1, 0, 2.0, 0.5);
+        let mut strategies = HashMap::new();
+        strategies.insert("root".to_string(), node);
+
+        let mut metadata =
+            CheckpointMetadata::new::<_, f32>(&KuhnPoker::new(), "cfr_plus".into(), "".into());
+        metadata.iteration = 42;
+        let path = std::env::temp_dir().join(name);
+        Checkpoint::save(&path, &metadata, &[1.5, -1.5], &strategies).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = save_test_checkpoint("cfr_checkpoint_test.ckpt");
+        let checkpoint = Checkpoint::<String, f32>::load_for_game(&path, &KuhnPoker::new());
+        let metadata = read_metadata(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let checkpoint = checkpoint.unwrap();
+        assert_eq!(checkpoint.metadata, metadata);
+        assert_eq!(checkpoint.metadata.iteration, 42);
+        assert_eq!(checkpoint.metadata.float_type, "f32");
+        assert_eq!(checkpoint.metadata.key_type, "string");
+        assert_eq!(checkpoint.cumulative_utility, vec![1.5, -1.5]);
+        assert_eq!(checkpoint.strategies["root"].get_strategy_sum(1), 3.0);
+        // Learned baselines are resumed along with the strategies
+        assert_eq!(checkpoint.strategies["root"].baseline(1), Some(&[1.0, 0.0][..]));
+    }
+
+    #[test]
+    fn test_rejects_mismatched_checkpoints() {
+        let path = save_test_checkpoint("cfr_checkpoint_mismatch_test.ckpt");
+        let wrong_float = Checkpoint::<String, f64>::load(&path);
+        let wrong_key = Checkpoint::<u64, f32>::load(&path);
+        let wrong_game = Checkpoint::<String, f32>::load_for_game(&path, &LeducHoldem::new());
+        let wrong_solver =
+            Checkpoint::<String, f32>::load_for_solver(&path, &KuhnPoker::new(), "discounted", "");
+        let wrong_params =
+            Checkpoint::<String, f32>::load_for_solver(&path, &KuhnPoker::new(), "cfr_plus", "x");
+        let same_solver =
+            Checkpoint::<String, f32>::load_for_solver(&path, &KuhnPoker::new(), "cfr_plus", "");
+        std::fs::write(&path, b"not a checkpoint").unwrap();
+        let not_checkpoint = Checkpoint::<String, f32>::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            wrong_float,
+            Err(CheckpointError::Mismatch {
+                field: "float type",
+                ..
+            })
+        ));
+        assert!(matches!(
+            wrong_key,
+            Err(CheckpointError::Mismatch {
+                field: "key type",
+                ..
+            })
+        ));
+        assert!(matches!(
+            wrong_game,
+            Err(CheckpointError::Mismatch { field: "game", .. })
+        ));
+        assert!(matches!(
+            wrong_solver,
+            Err(CheckpointError::Mismatch {
+                field: "solver",
+                ..
+            })
+        ));
+        assert!(matches!(
+            wrong_params,
+            Err(CheckpointError::Mismatch {
+                field: "solver parameters",
+                ..
+            })
+        ));
+        assert!(same_solver.is_ok());
+        assert!(matches!(
+            not_checkpoint,
+            Err(CheckpointError::NotACheckpoint)
+        ));
+    }
+
+    #[test]
+    fn test_failed_save_removes_temporary_file() {
+        // Renaming the finished checkpoint over a directory fails
+        let path = std::env::temp_dir().join("cfr_checkpoint_failed_save_test");
+        std::fs::create_dir_all(&path).unwrap();
+        let metadata =
+            CheckpointMetadata::new::<_, f32>(&KuhnPoker::new(), "cfr_plus".into(), "".into());
+        let strategies = HashMap::<String, StateNode<f32>>::new();
+        let result = Checkpoint::save(&path, &metadata, &[0.0, 0.0], &strategies);
+        let tmp_exists = path.with_extension("tmp").exists();
+        std::fs::remove_dir(&path).unwrap();
+
+        assert!(matches!(result, Err(CheckpointError::Io(_))));
+        assert!(!tmp_exists);
+    }
+}
@@ -0,0 +1,450 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+use super::metrics::MetricsFormat;
+use super::solvers::Baseline;
+
+/// Schedule used to decay the exploration of the sampling solvers over the course of training
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EpsilonSchedule {
+    /// Exploration stays the same on every iteration
+    Constant,
+    /// Exploration decreases linearly until it reaches `min_epsilon` after `steps` iterations
+    Linear { min_epsilon: f32, steps: usize },
+    /// Exploration is multiplied by `decay` on every iteration until it reaches `min_epsilon`
+    Exponential { min_epsilon: f32, decay: f32 },
+}
+
+impl fmt::Display for EpsilonSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpsilonSchedule::Constant => write!(f, "constant"),
+            EpsilonSchedule::Linear { min_epsilon, steps } => {
+                write!(f, "linear(min_epsilon={}, steps={})", min_epsilon, steps)
+            }
+            EpsilonSchedule::Exponential { min_epsilon, decay } => {
+                write!(f, "exponential(min_epsilon={}, decay={})", min_epsilon, decay)
+            }
+        }
+    }
+}
+
+/// How each iteration contributes to the average strategy
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AveragingMode {
+    /// Every iteration is weighted equally
+    Uniform,
+    /// Iteration t is weighted by t, which discounts the poor strategies from early iterations
+    Linear,
+}
+
+impl fmt::Display for AveragingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AveragingMode::Uniform => write!(f, "uniform"),
+            AveragingMode::Linear => write!(f, "linear"),
+        }
+    }
+}
+
+/// Hyperparameters of the sampling based solvers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolverConfig {
+    /// Probability of exploring a uniformly random action when sampling the updating player's
+    /// actions in outcome sampling
+    pub epsilon: f32,
+    /// Schedule used to decay the exploration probability
+    pub epsilon_schedule: EpsilonSchedule,
+    /// Lower bound on the sampling probability used to importance weight sampled values. This
+    /// prevents the values from exploding when very unlikely histories are sampled
+    pub reach_clip: f32,
+    /// How each iteration contributes to the average strategy
+    pub averaging: AveragingMode,
+    /// Baseline used to reduce the variance of outcome sampling
+    pub baseline: Baseline,
+    /// If set, the least visited states are evicted once the strategies use more than this many
+    /// bytes. Only supported by the sequential outcome sampling solver
+    pub max_memory_bytes: Option<usize>,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            epsilon: 0.6,
+            epsilon_schedule: EpsilonSchedule::Constant,
+            reach_clip: 1e-12,
+            averaging: AveragingMode::Uniform,
+            baseline: Baseline::Zero,
+            max_memory_bytes: None,
+        }
+    }
+}
+
+impl SolverConfig {
+    /// Loads the config from a JSON file. Missing fields use their default values
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let config: Self = serde_json::from_reader(BufReader::new(file))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that every probability is within its range and that the schedules make progress
+    pub fn validate(&self) -> Result<()> {
+        check(
+            self.epsilon > 0.0 && self.epsilon <= 1.0,
+            "epsilon must be in (0, 1]",
+        )?;
+        match self.epsilon_schedule {
+            EpsilonSchedule::Constant => {}
+            EpsilonSchedule::Linear { min_epsilon, steps } => {
+                check(
+                    min_epsilon > 0.0 && min_epsilon <= 1.0,
+                    "min_epsilon must be in (0, 1]",
+                )?;
+                check(steps > 0, "epsilon schedule steps must be greater than 0")?;
+            }
+            EpsilonSchedule::Exponential { min_epsilon, decay } => {
+                check(
+                    min_epsilon > 0.0 && min_epsilon <= 1.0,
+                    "min_epsilon must be in (0, 1]",
+                )?;
+                check(decay > 0.0 && decay <= 1.0, "epsilon decay must be in (0, 1]")?;
+            }
+        }
+        check(self.reach_clip > 0.0, "reach_clip must be greater than 0")?;
+        if let Baseline::Learned(alpha) = self.baseline {
+            check(
+                alpha > 0.0 && alpha <= 1.0,
+                "baseline learning rate must be in (0, 1]",
+            )?;
+        }
+        check(
+            self.max_memory_bytes != Some(0),
+            "max_memory_bytes must be greater than 0",
+        )
+    }
+
+    /// Formats the parameters that change what outcome sampling learns for checkpoints. Settings
+    /// that do not, like the memory limit, are left out so changing them does not stop training
+    /// from being resumed
+    pub fn outcome_sampling_params(&self) -> String {
+        format!(
+            "epsilon={}, epsilon_schedule={}, reach_clip={}, averaging={}, baseline={}",
+            self.epsilon, self.epsilon_schedule, self.reach_clip, self.averaging, self.baseline
+        )
+    }
+
+    /// Formats the parameters that change what external sampling learns for checkpoints, which
+    /// only samples according to the current strategy and so is only affected by the averaging
+    pub fn external_sampling_params(&self) -> String {
+        format!("averaging={}", self.averaging)
+    }
+
+    /// Gets the exploration probability on the provided (1-based) iteration
+    pub fn epsilon_at(&self, iteration: usize) -> f32 {
+        let elapsed = iteration.saturating_sub(1);
+        match self.epsilon_schedule {
+            EpsilonSchedule::Constant => self.epsilon,
+            EpsilonSchedule::Linear { min_epsilon, steps } => {
+                let progress = (elapsed as f32 / steps.max(1) as f32).min(1.0);
+                self.epsilon + (min_epsilon - self.epsilon) * progress
+            }
+            EpsilonSchedule::Exponential { min_epsilon, decay } => {
+                // Clamped so the exponent does not wrap around on very long runs
+                let exponent = elapsed.min(i32::MAX as usize) as i32;
+                (self.epsilon * decay.powi(exponent)).max(min_epsilon)
+            }
+        }
+    }
+
+    /// Gets the weight of contributions to the average strategy on the provided iteration
+    pub fn averaging_weight(&self, iteration: usize) -> f32 {
+        match self.averaging {
+            AveragingMode::Uniform => 1.0,
+            AveragingMode::Linear => iteration as f32,
+        }
+    }
+}
+
+/// Settings that control how often the trainer logs progress and saves checkpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrainerConfig {
+    /// Progress is printed every this many rounds
+    pub print_steps: usize,
+    /// If set, the strategies are saved every this many rounds
+    pub ckpt_steps: Option<usize>,
+    /// Directory that checkpoints are saved to
+    pub ckpt_dir: PathBuf,
+    /// File name prefix of the checkpoints. Each checkpoint is saved as `<name>_<iteration>.ckpt`
+    pub ckpt_name: String,
+    /// If set, only this many of the most recent checkpoints are kept and older ones are deleted
+    pub keep_ckpts: Option<usize>,
+    /// If set, the strategy with the lowest exploitability seen so far is also saved to
+    /// `<name>_best.ckpt`. Requires `eval_steps` to be set
+    pub keep_best_ckpt: bool,
+    /// If set, the exploitability of the average strategy is logged every this many rounds.
+    /// Computing the exploitability enumerates the whole game tree, so this should only be used
+    /// on small games
+    pub eval_steps: Option<usize>,
+    /// Number of threads used by parallel training. Uses every core if not set
+    pub num_threads: Option<usize>,
+    /// If set, a record of the training progress is appended to this file every time progress
+    /// is printed or the strategy is evaluated
+    pub metrics_path: Option<PathBuf>,
+    /// Format of the metrics file
+    pub metrics_format: MetricsFormat,
+    /// Seed of the random number generator used for training. Training runs with the same seed
+    /// and config are identical when run sequentially. A random seed is picked if not set
+    pub seed: Option<u64>,
+}
+
+impl Default for TrainerConfig {
+    fn default() -> Self {
+        Self {
+            print_steps: 1000,
+            ckpt_steps: None,
+            ckpt_dir: PathBuf::from("./strategies"),
+            ckpt_name: "strategy".to_string(),
+            keep_ckpts: Some(3),
+            keep_best_ckpt: false,
+            eval_steps: None,
+            num_threads: None,
+            metrics_path: None,
+            metrics_format: MetricsFormat::Csv,
+            seed: None,
+        }
+    }
+}
+
+impl TrainerConfig {
+    /// Loads the config from a JSON file. Missing fields use their default values
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let config: Self = serde_json::from_reader(BufReader::new(file))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that every step count is greater than 0 and that the checkpoint settings can be
+    /// followed
+    pub fn validate(&self) -> Result<()> {
+        check(self.print_steps > 0, "print_steps must be greater than 0")?;
+        check(
+            self.ckpt_steps != Some(0),
+            "ckpt_steps must be greater than 0",
+        )?;
+        check(
+            self.eval_steps != Some(0),
+            "eval_steps must be greater than 0",
+        )?;
+        check(
+            self.keep_ckpts != Some(0),
+            "keep_ckpts must be greater than 0",
+        )?;
+        check(
+            !self.keep_best_ckpt || self.eval_steps.is_some(),
+            "keep_best_ckpt requires eval_steps to be set",
+        )
+    }
+
+    /// Path of the checkpoint taken after the provided number of rounds
+    pub fn ckpt_path(&self, iteration: usize) -> PathBuf {
+        self.ckpt_dir.join(format!("{}_{}.ckpt", self.ckpt_name, iteration))
+    }
+
+    /// Path of the checkpoint with the lowest exploitability
+    pub fn best_ckpt_path(&self) -> PathBuf {
+        self.ckpt_dir.join(format!("{}_best.ckpt", self.ckpt_name))
+    }
+
+    /// Finds the checkpoints in the checkpoint directory, sorted by the number of rounds they
+    /// were taken after
+    pub fn list_ckpts(&self) -> Result<Vec<(usize, PathBuf)>> {
+        if !self.ckpt_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let prefix = format!("{}_", self.ckpt_name);
+        let mut ckpts = Vec::new();
+        for entry in fs::read_dir(&self.ckpt_dir)? {
+            let path = entry?.path();
+            let iteration = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(".ckpt"))
+                .and_then(|iteration| iteration.parse::<usize>().ok());
+            if let Some(iteration) = iteration {
+                ckpts.push((iteration, path));
+            }
+        }
+        ckpts.sort_unstable();
+        Ok(ckpts)
+    }
+
+    /// Path of the most recent checkpoint, if there is one
+    pub fn latest_ckpt(&self) -> Result<Option<PathBuf>> {
+        Ok(self.list_ckpts()?.pop().map(|(_, path)| path))
+    }
+}
+
+/// Fails with the provided message if the condition does not hold
+fn check(condition: bool, message: &str) -> Result<()> {
+    if condition {
+        Ok(())
+    } else {
+        Err(Error::Config(message.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfr::solvers::Baseline;
+    use crate::error::Error;
+
+    use super::{AveragingMode, EpsilonSchedule, SolverConfig, TrainerConfig};
+
+    #[test]
+    fn test_epsilon_schedules() {
+        let mut config = SolverConfig {
+            epsilon: 0.6,
+            epsilon_schedule: EpsilonSchedule::Linear {
+                min_epsilon: 0.1,
+                steps: 10,
+            },
+            ..Default::default()
+        };
+        assert_eq!(config.epsilon_at(1), 0.6);
+        assert!((config.epsilon_at(6) - 0.35).abs() < 1e-6);
+        assert!((config.epsilon_at(100) - 0.1).abs() < 1e-6);
+
+        config.epsilon_schedule = EpsilonSchedule::Exponential {
+            min_epsilon: 0.1,
+            decay: 0.5,
+        };
+        assert!((config.epsilon_at(2) - 0.3).abs() < 1e-6);
+        assert!((config.epsilon_at(100) - 0.1).abs() < 1e-6);
+        assert!((config.epsilon_at(usize::MAX) - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_params_leave_out_memory_limit() {
+        let config = SolverConfig {
+            epsilon_schedule: EpsilonSchedule::Linear {
+                min_epsilon: 0.1,
+                steps: 10,
+            },
+            baseline: Baseline::Learned(0.5),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.outcome_sampling_params(),
+            "epsilon=0.6, epsilon_schedule=linear(min_epsilon=0.1, steps=10), \
+             reach_clip=0.000000000001, averaging=uniform, baseline=learned(0.5)"
+        );
+        assert_eq!(config.external_sampling_params(), "averaging=uniform");
+
+        let limited = SolverConfig {
+            max_memory_bytes: Some(1 << 20),
+            ..config.clone()
+        };
+        assert_eq!(
+            limited.outcome_sampling_params(),
+            config.outcome_sampling_params()
+        );
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let solver: SolverConfig =
+            serde_json::from_str(r#"{ "epsilon": 0.3, "averaging": "Linear" }"#).unwrap();
+        assert_eq!(solver.epsilon, 0.3);
+        assert_eq!(solver.averaging, AveragingMode::Linear);
+        assert_eq!(solver.epsilon_schedule, EpsilonSchedule::Constant);
+
+        let trainer: TrainerConfig =
+            serde_json::from_str(r#"{ "ckpt_steps": 100, "ckpt_name": "kuhn" }"#).unwrap();
+        assert_eq!(trainer.ckpt_steps, Some(100));
+        assert_eq!(trainer.eval_steps, None);
+        assert!(trainer.ckpt_path(100).ends_with("kuhn_100.ckpt"));
+    }
+
+    #[test]
+    fn test_invalid_files_return_errors() {
+        let missing = SolverConfig::from_file("does_not_exist.json");
+        assert!(matches!(missing, Err(Error::Io(_))));
+
+        let path = std::env::temp_dir().join("cfr_invalid_config_test.json");
+        std::fs::write(&path, "{ \"print_steps\": ").unwrap();
+        let invalid = TrainerConfig::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(invalid, Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn test_rejects_invalid_values() {
+        assert!(SolverConfig::default().validate().is_ok());
+        assert!(TrainerConfig::default().validate().is_ok());
+
+        let invalid_solvers = [
+            SolverConfig {
+                epsilon: 0.0,
+                ..Default::default()
+            },
+            SolverConfig {
+                epsilon: 1.5,
+                ..Default::default()
+            },
+            SolverConfig {
+                epsilon_schedule: EpsilonSchedule::Linear {
+                    min_epsilon: 0.1,
+                    steps: 0,
+                },
+                ..Default::default()
+            },
+            SolverConfig {
+                max_memory_bytes: Some(0),
+                ..Default::default()
+            },
+        ];
+        for config in invalid_solvers {
+            assert!(matches!(config.validate(), Err(Error::Config(_))));
+        }
+
+        let invalid_trainers = [
+            TrainerConfig {
+                print_steps: 0,
+                ..Default::default()
+            },
+            TrainerConfig {
+                ckpt_steps: Some(0),
+                ..Default::default()
+            },
+            TrainerConfig {
+                eval_steps: Some(0),
+                ..Default::default()
+            },
+            TrainerConfig {
+                keep_ckpts: Some(0),
+                ..Default::default()
+            },
+        ];
+        for config in invalid_trainers {
+            assert!(matches!(config.validate(), Err(Error::Config(_))));
+        }
+
+        let path = std::env::temp_dir().join("cfr_zero_steps_config_test.json");
+        std::fs::write(&path, r#"{ "print_steps": 0 }"#).unwrap();
+        let invalid = TrainerConfig::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(invalid, Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use ndarray::NdFloat;
+
+use super::node::StateNode;
+use super::state::{Game, GameState};
+
+/// Summary of how far a strategy profile is from a Nash equilibrium
+#[derive(Debug, Clone)]
+pub struct ExploitabilityReport {
+    /// Expected value of each player when everyone follows the average strategy
+    pub values: Vec<f64>,
+    /// Expected value of each player when they play a best response against the average
+    /// strategy of everyone else
+    pub best_response_values: Vec<f64>,
+    /// Sum of how much each player could gain by deviating to a best response
+    pub nash_conv: f64,
+    /// NashConv averaged over the number of players
+    pub exploitability: f64,
+}
+
+/// Computes the exploitability (NashConv) of the average strategies in the provided table.
+/// This enumerates the entire game tree (including chance nodes) so it is only feasible for
+/// small games such as tic-tac-toe or the toy poker games. States without a strategy node are
+/// assumed to play uniformly at random across their valid actions
+pub fn exploitability<G, A>(
+    game: &G,
+    strategies: &HashMap<<G::State as GameState>::Key, StateNode<A>>,
+) -> ExploitabilityReport
+where
+    G: Game,
+    A: NdFloat,
+{
+    let values = expected_values(game, strategies);
+    let best_response_values = (0..game.num_players())
+        .map(|p| best_response_value(game, strategies, p))
+        .collect::<Vec<_>>();
+
+    let nash_conv = best_response_values
+        .iter()
+        .zip(values.iter())
+        .map(|(br, v)| br - v)
+        .sum::<f64>();
+
+    ExploitabilityReport {
+        values,
+        best_response_values,
+        nash_conv,
+        exploitability: nash_conv / game.num_players() as f64,
+    }
+}
+
+/// Computes the expected value of every player when all players follow the average strategy
+pub fn expected_values<G, A>(
+    game: &G,
+    strategies: &HashMap<<G::State as GameState>::Key, StateNode<A>>,
+) -> Vec<f64>
+where
+    G: Game,
+    A: NdFloat,
+{
+    let policy = AveragePolicy { strategies };
+    policy.expected_values(&game.start(), game.num_players())
+}
+
+/// Computes the value the provided player obtains by playing a best response against the
+/// average strategy of all other players
+pub fn best_response_value<G, A>(
+    game: &G,
+    strategies: &HashMap<<G::State as GameState>::Key, StateNode<A>>,
+    player: usize,
+) -> f64
+where
+    G: Game,
+    A: NdFloat,
+{
+    let initial_state = game.start();
+    let mut best_response = BestResponse {
+        policy: AveragePolicy { strategies },
+        player,
+        info_sets: HashMap::new(),
+        best_actions: HashMap::new(),
+    };
+    best_response.collect_info_sets(&initial_state, 1.0);
+    best_response.value(&initial_state)
+}
+
+/// Wrapper that plays the normalized average strategy of the strategy table
+struct AveragePolicy<'a, S: GameState, A> {
+    strategies: &'a HashMap<S::Key, StateNode<A>>,
+}
+
+impl<'a, S: GameState, A: NdFloat> AveragePolicy<'a, S, A> {
+    /// Returns the probability of playing each valid action in the provided state
+    fn action_probabilities(&self, state: &S) -> Vec<(usize, f64)> {
+        let valid_actions = state.valid_actions();
+        let key = state.information_set_key(state.active_player());
+        let mut probs = valid_actions.iter().map(|&a| (a, 0.0)).collect::<Vec<_>>();
+        if let Some(node) = self.strategies.get(&key) {
+            let avg_strategy = node.get_average_strategy();
+            for (a, p) in probs.iter_mut() {
+                if let Ok(i) = node.actions().binary_search(a) {
+                    *p = avg_strategy[i].to_f64().unwrap();
+                }
+            }
+        }
+
+        // Only valid actions can be played, so the strategy is renormalized over them
+        let total = probs.iter().map(|(_, p)| p).sum::<f64>();
+        for (_, p) in probs.iter_mut() {
+            *p = if total > 0.0 {
+                *p / total
+            } else {
+                1.0 / valid_actions.len() as f64
+            };
+        }
+        probs
+    }
+
+    fn expected_values(&self, state: &S, num_players: usize) -> Vec<f64> {
+        if state.is_terminal() {
+            return (0..num_players)
+                .map(|p| state.get_reward(p) as f64)
+                .collect();
+        }
+
+        let transitions = if state.is_chance() {
+            state
+                .chance_outcomes()
+                .into_iter()
+                .map(|(o, p)| (state.apply_chance_outcome(o), p as f64))
+                .collect::<Vec<_>>()
+        } else {
+            self.action_probabilities(state)
+                .into_iter()
+                .map(|(a, p)| (state.next_state(a).unwrap(), p))
+                .collect::<Vec<_>>()
+        };
+
+        let mut values = vec![0.0; num_players];
+        for (next_state, prob) in transitions {
+            for (v, child) in values
+                .iter_mut()
+                .zip(self.expected_values(&next_state, num_players))
+            {
+                *v += prob * child;
+            }
+        }
+        values
+    }
+}
+
+/// Computes a best response for a single player against a fixed policy for everyone else.
+/// Based on https://github.com/deepmind/open_spiel/blob/master/open_spiel/python/algorithms/best_response.py
+struct BestResponse<'a, S: GameState, A> {
+    /// Policy followed by every other player
+    policy: AveragePolicy<'a, S, A>,
+    /// Player that is best responding
+    player: usize,
+    /// Every state in each of the player's information sets along with the probability of
+    /// reaching it due to chance and the other players
+    info_sets: HashMap<S::Key, Vec<(S, f64)>>,
+    /// Cached best action for each information set of the player
+    best_actions: HashMap<S::Key, usize>,
+}
+
+impl<'a, S: GameState, A: NdFloat> BestResponse<'a, S, A> {
+    /// Walks the entire game tree and groups the player's states by information set
+    fn collect_info_sets(&mut self, state: &S, reach_other: f64) {
+        if state.is_terminal() {
+            return;
+        }
+
+        if state.is_chance() {
+            for (outcome, prob) in state.chance_outcomes() {
+                let next_state = state.apply_chance_outcome(outcome);
+                self.collect_info_sets(&next_state, reach_other * prob as f64);
+            }
+        } else if state.active_player() == self.player {
+            let key = state.information_set_key(self.player);
+            self.info_sets
+                .entry(key)
+                .or_default()
+                .push((state.clone(), reach_other));
+            for a in state.valid_actions() {
+                self.collect_info_sets(&state.next_state(a).unwrap(), reach_other);
+            }
+        } else {
+            for (a, prob) in self.policy.action_probabilities(state) {
+                self.collect_info_sets(&state.next_state(a).unwrap(), reach_other * prob);
+            }
+        }
+    }
+
+    /// Value of the state for the player when they play the best response
+    fn value(&mut self, state: &S) -> f64 {
+        if state.is_terminal() {
+            return state.get_reward(self.player) as f64;
+        }
+
+        if state.is_chance() {
+            return state
+                .chance_outcomes()
+                .into_iter()
+                .map(|(o, p)| p as f64 * self.value(&state.apply_chance_outcome(o)))
+                .sum();
+        }
+
+        if state.active_player() == self.player {
+            let action = self.best_action(state.information_set_key(self.player));
+            return self.value(&state.next_state(action).unwrap());
+        }
+
+        self.policy
+            .action_probabilities(state)
+            .into_iter()
+            .map(|(a, p)| p * self.value(&state.next_state(a).unwrap()))
+            .sum()
+    }
+
+    /// Picks the action that maximizes the counterfactual value across every state in the
+    /// information set
+    fn best_action(&mut self, key: S::Key) -> usize {
+        if let Some(&action) = self.best_actions.get(&key) {
+            return action;
+        }
+
+        // Once the best action is cached the states are no longer needed
+        let states = self.info_sets.remove(&key).unwrap_or_default();
+        let mut best_action = states[0].0.valid_actions()[0];
+        let mut best_value = f64::NEG_INFINITY;
+        for a in states[0].0.valid_actions() {
+            let value = states
+                .iter()
+                .map(|(s, reach)| reach * self.value(&s.next_state(a).unwrap()))
+                .sum::<f64>();
+            if value > best_value {
+                best_value = value;
+                best_action = a;
+            }
+        }
+
+        self.best_actions.insert(key, best_action);
+        best_action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cfr::node::StateNode;
+    use crate::cfr::state::{Game, GameState};
+
+    use super::{best_response_value, exploitability};
+
+    /// Rock-paper-scissors played sequentially, where the second player cannot observe the
+    /// move of the first player
+    #[derive(Debug, Clone)]
+    struct RpsState {
+        moves: Vec<usize>,
+    }
+
+    impl GameState for RpsState {
+        type Key = String;
+
+        fn active_player(&self) -> usize {
+            self.moves.len()
+        }
+
+        fn valid_actions(&self) -> Vec<usize> {
+            vec![0, 1, 2]
+        }
+
+        fn state_key(&self) -> Self::Key {
+            format!("{:?}", self.moves)
+        }
+
+        fn information_set_key(&self, player: usize) -> Self::Key {
+            format!("{}", player)
+        }
+
+        fn next_state(&self, action: usize) -> Option<Self> {
+            let mut moves = self.moves.clone();
+            moves.push(action);
+            Some(RpsState { moves })
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.moves.len() == 2
+        }
+
+        fn get_reward(&self, player: usize) -> f32 {
+            let (mine, theirs) = (self.moves[player], self.moves[1 - player]);
+            if mine == theirs {
+                0.0
+            } else if (mine + 2) % 3 == theirs {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+    }
+
+    struct Rps;
+
+    impl Game for Rps {
+        type State = RpsState;
+
+        fn num_players(&self) -> usize {
+            2
+        }
+
+        fn num_actions(&self) -> usize {
+            3
+        }
+
+        fn start(&self) -> Self::State {
+            RpsState { moves: vec![] }
+        }
+
+        fn reset(&mut self) {}
+
+        fn name(&self) -> String {
+            "rps".to_string()
+        }
+    }
+
+    #[test]
+    fn test_uniform_is_unexploitable() {
+        let strategies = HashMap::<String, StateNode<f32>>::new();
+        let report = exploitability(&Rps, &strategies);
+
+        assert!(report.nash_conv.abs() < 1e-6);
+        assert!(report.values.iter().all(|v| v.abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_best_response_exploits_pure_strategy() {
+        // The first player always plays rock
+        let mut node = StateNode::<f32>::new(3);
+        node.update_strategy_sum(0, 1.0);
+        let mut strategies = HashMap::new();
+        strategies.insert("0".to_string(), node);
+
+        assert!((best_response_value(&Rps, &strategies, 1) - 1.0).abs() < 1e-6);
+        let report = exploitability(&Rps, &strategies);
+        assert!((report.nash_conv - 1.0).abs() < 1e-6);
+        assert!((report.exploitability - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ignores_strategy_of_invalid_actions() {
+        // Most of the average strategy is on an action that is not valid in the state, so the
+        // first player only ever plays rock
+        let mut node = StateNode::<f32>::new(5);
+        node.update_strategy_sum(0, 1.0);
+        node.update_strategy_sum(4, 3.0);
+        let mut strategies = HashMap::new();
+        strategies.insert("0".to_string(), node);
+
+        assert!((best_response_value(&Rps, &strategies, 1) - 1.0).abs() < 1e-6);
+    }
+}
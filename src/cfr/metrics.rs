@@ -0,0 +1,208 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::mem::size_of;
+use std::path::Path;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+use super::node::StateNode;
+
+/// File format of the training metrics
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MetricsFormat {
+    /// Comma separated values with a header row. Utilities get one column per player
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Snapshot of the progress of training at a given round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsRecord {
+    /// Number of rounds completed
+    pub iteration: usize,
+    /// Utility of each player summed over all completed rounds
+    pub cumulative_utility: Vec<f64>,
+    /// Utility of each player summed over the rounds since the previous record
+    pub window_utility: Vec<f64>,
+    /// Number of states that have a strategy
+    pub visited_states: usize,
+    /// Rounds completed per second since the previous record
+    pub iterations_per_sec: f64,
+    /// Rough estimate of the memory used by the strategies, in bytes
+    pub memory_bytes: usize,
+    /// Number of states evicted from the strategies to stay under their memory limit so far
+    pub evicted_states: usize,
+    /// Exploitability of the average strategy, if it was evaluated on this round
+    pub exploitability: Option<f64>,
+}
+
+/// Rough estimate of the memory used to store the strategy of a single state along with its
+/// key. Memory owned by the key itself (i.e. the contents of a `String`) is not included
+pub fn estimate_memory_bytes<K, A>(node: &StateNode<A>) -> usize {
+    size_of::<K>() + node.memory_bytes()
+}
+
+/// Appends a record of the training progress to a file every time it is logged, so convergence
+/// can be plotted while training is still running
+pub struct MetricsLogger {
+    writer: BufWriter<File>,
+    format: MetricsFormat,
+    /// Round and time of the previous record, used to compute the per-window values
+    last_iteration: usize,
+    last_time: Instant,
+    last_utility: Vec<f64>,
+}
+
+impl MetricsLogger {
+    /// Opens the metrics file, appending to it if it already exists (i.e. when training resumes
+    /// from a checkpoint). The provided round and utilities are the starting point of the first
+    /// window
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        format: MetricsFormat,
+        iteration: usize,
+        cumulative_utility: Vec<f64>,
+    ) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if format == MetricsFormat::Csv && is_empty {
+            writeln!(writer, "{}", Self::csv_header(cumulative_utility.len()))?;
+            writer.flush()?;
+        }
+        Ok(Self {
+            writer,
+            format,
+            last_iteration: iteration,
+            last_time: Instant::now(),
+            last_utility: cumulative_utility,
+        })
+    }
+
+    /// Writes a record of the current progress and starts a new window
+    pub fn log(
+        &mut self,
+        iteration: usize,
+        cumulative_utility: Vec<f64>,
+        visited_states: usize,
+        memory_bytes: usize,
+        evicted_states: usize,
+        exploitability: Option<f64>,
+    ) -> Result<MetricsRecord> {
+        let elapsed = self.last_time.elapsed().as_secs_f64();
+        let rounds = iteration - self.last_iteration;
+        let window_utility = cumulative_utility
+            .iter()
+            .zip(self.last_utility.iter())
+            .map(|(curr, last)| curr - last)
+            .collect();
+        let record = MetricsRecord {
+            iteration,
+            cumulative_utility,
+            window_utility,
+            visited_states,
+            iterations_per_sec: if elapsed > 0.0 {
+                rounds as f64 / elapsed
+            } else {
+                0.0
+            },
+            memory_bytes,
+            evicted_states,
+            exploitability,
+        };
+
+        match self.format {
+            MetricsFormat::Csv => writeln!(self.writer, "{}", Self::csv_row(&record))?,
+            MetricsFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, &record)?;
+                writeln!(self.writer)?;
+            }
+        }
+        // Flush every record so the file can be read while training
+        self.writer.flush()?;
+
+        self.last_iteration = iteration;
+        self.last_time = Instant::now();
+        self.last_utility = record.cumulative_utility.clone();
+        Ok(record)
+    }
+
+    fn csv_header(num_players: usize) -> String {
+        let mut columns = vec!["iteration".to_string()];
+        columns.extend((0..num_players).map(|p| format!("cumulative_utility_{}", p)));
+        columns.extend((0..num_players).map(|p| format!("window_utility_{}", p)));
+        columns.push("visited_states".to_string());
+        columns.push("iterations_per_sec".to_string());
+        columns.push("memory_bytes".to_string());
+        columns.push("evicted_states".to_string());
+        columns.push("exploitability".to_string());
+        columns.join(",")
+    }
+
+    fn csv_row(record: &MetricsRecord) -> String {
+        let mut columns = vec![record.iteration.to_string()];
+        columns.extend(record.cumulative_utility.iter().map(|u| u.to_string()));
+        columns.extend(record.window_utility.iter().map(|u| u.to_string()));
+        columns.push(record.visited_states.to_string());
+        columns.push(record.iterations_per_sec.to_string());
+        columns.push(record.memory_bytes.to_string());
+        columns.push(record.evicted_states.to_string());
+        // Rounds that were not evaluated leave the column empty
+        columns.push(
+            record
+                .exploitability
+                .map_or(String::new(), |e| e.to_string()),
+        );
+        columns.join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MetricsFormat, MetricsLogger, MetricsRecord};
+
+    #[test]
+    fn test_writes_records() {
+        let csv_path = std::env::temp_dir().join("cfr_metrics_test.csv");
+        let jsonl_path = std::env::temp_dir().join("cfr_metrics_test.jsonl");
+        let _ = std::fs::remove_file(&csv_path);
+        let _ = std::fs::remove_file(&jsonl_path);
+
+        let mut csv =
+            MetricsLogger::create(&csv_path, MetricsFormat::Csv, 0, vec![0.0; 2]).unwrap();
+        csv.log(10, vec![2.0, -2.0], 5, 100, 0, None).unwrap();
+        csv.log(20, vec![3.0, -3.0], 6, 120, 0, Some(0.25)).unwrap();
+        let mut jsonl =
+            MetricsLogger::create(&jsonl_path, MetricsFormat::JsonLines, 0, vec![0.0; 2]).unwrap();
+        jsonl.log(10, vec![2.0, -2.0], 5, 100, 0, None).unwrap();
+        jsonl.log(20, vec![3.0, -3.0], 6, 120, 0, Some(0.25)).unwrap();
+
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        let jsonl = std::fs::read_to_string(&jsonl_path).unwrap();
+        std::fs::remove_file(&csv_path).unwrap();
+        std::fs::remove_file(&jsonl_path).unwrap();
+
+        let rows = csv
+            .lines()
+            .map(|l| l.split(',').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][0], "iteration");
+        assert_eq!(rows[0].len(), rows[1].len());
+        assert_eq!(&rows[2][..5], &["20", "3", "-3", "1", "-1"]);
+        assert_eq!(rows[1].last(), Some(&""));
+        assert_eq!(rows[2].last(), Some(&"0.25"));
+
+        let records = jsonl
+            .lines()
+            .map(|l| serde_json::from_str::<MetricsRecord>(l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].window_utility, vec![1.0, -1.0]);
+        assert_eq!(records[1].exploitability, Some(0.25));
+    }
+}
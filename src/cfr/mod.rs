@@ -1,7 +1,13 @@
-mod solvers;
+pub mod checkpoint;
+pub mod config;
+pub mod eval;
+pub mod metrics;
+pub mod solvers;
 pub mod trainer;
 pub mod node;
 pub mod state;
+pub mod store;
+pub mod strategy_file;
 
 
 pub use trainer::CFRTrainer;
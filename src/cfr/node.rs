@@ -1,36 +1,94 @@
+use std::mem::size_of;
+
 use ndarray::prelude::*;
 use ndarray_rand::rand_distr::num_traits::Zero;
-use ndarray_stats::QuantileExt;
 use serde::{Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize)]
+/// Regrets and strategies of a single information set. Entries are only stored for the actions
+/// that are valid in the state, which is far smaller than the number of actions in the whole
+/// game for games like scrabble. Actions are always referred to by their id in the whole game,
+/// and any action without an entry has no regret and is never played
+#[derive(Clone, Serialize, Deserialize)]
 pub struct StateNode<A> {
-    /// Number of available actions
+    /// Number of actions in the entire game
     num_actions: usize,
-    /// Sum of all the regrets for each action
-    regret_sum: Array1<A>,
-    /// Strategy to be played in this state
-    strategy: Array1<A>,
-    /// Sum of all the strategy logits for this node
-    strategy_sum: Array1<A>,
+    /// Ids of the actions that have an entry, in ascending order
+    actions: Vec<usize>,
+    /// Sum of all the regrets for each stored action
+    regret_sum: Vec<A>,
+    /// Strategy to be played in this state for each stored action
+    strategy: Vec<A>,
+    /// Sum of all the strategy logits for each stored action
+    strategy_sum: Vec<A>,
+    /// Learned baseline of each stored action from the perspective of each player. Players are
+    /// only given baselines once one of theirs is updated
+    baselines: Vec<Vec<A>>,
 }
 
-impl<A> StateNode<A> 
+impl<A> StateNode<A>
 where
     A: NdFloat + Zero
 {
+    /// Creates a node with an entry for every action in the game
     pub fn new(num_actions: usize) -> Self {
+        let actions = (0..num_actions).collect::<Vec<_>>();
+        Self::with_actions(num_actions, &actions)
+    }
+
+    /// Creates a node that only has entries for the provided actions, i.e. the valid actions of
+    /// the state
+    pub fn with_actions(num_actions: usize, actions: &[usize]) -> Self {
+        let mut actions = actions.to_vec();
+        actions.sort_unstable();
+        actions.dedup();
+        let len = actions.len();
         Self {
             num_actions,
-            regret_sum: Array1::zeros(num_actions),
-            strategy: Array1::zeros(num_actions),
-            strategy_sum: Array1::zeros(num_actions),
+            actions,
+            regret_sum: vec![A::zero(); len],
+            strategy: vec![A::zero(); len],
+            strategy_sum: vec![A::zero(); len],
+            baselines: Vec::new(),
+        }
+    }
+
+    /// Number of actions in the entire game
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
+    }
+
+    /// Ids of the actions that have an entry in this node
+    pub fn actions(&self) -> &[usize] {
+        &self.actions
+    }
+
+    /// Gets the position of the action's entry, if it has one
+    fn index(&self, action: usize) -> Option<usize> {
+        self.actions.binary_search(&action).ok()
+    }
+
+    /// Gets the position of the action's entry, adding an empty entry if it does not have one
+    fn entry(&mut self, action: usize) -> usize {
+        match self.actions.binary_search(&action) {
+            Ok(i) => i,
+            Err(i) => {
+                self.actions.insert(i, action);
+                self.regret_sum.insert(i, A::zero());
+                self.strategy.insert(i, A::zero());
+                self.strategy_sum.insert(i, A::zero());
+                for baseline in self.baselines.iter_mut().filter(|b| !b.is_empty()) {
+                    baseline.insert(i, A::zero());
+                }
+                i
+            }
         }
     }
 
-    pub fn compute_strategy(&mut self) -> ArrayView1<A> {
+    /// Computes the current strategy with regret matching across the stored actions. The
+    /// returned probabilities line up with `actions()`
+    pub fn compute_strategy(&mut self) -> &[A] {
         let mut normalizing_sum = A::zero();
-        for i in 0..self.num_actions {
+        for i in 0..self.actions.len() {
             self.strategy[i] = if self.regret_sum[i] > A::zero() {
                 self.regret_sum[i]
             } else {
@@ -39,77 +97,189 @@ where
             normalizing_sum += self.strategy[i];
         }
 
-        for i in 0..self.num_actions {
+        for i in 0..self.actions.len() {
             if normalizing_sum > A::zero() {
                 self.strategy[i] /= normalizing_sum;
             } else {
-                self.strategy[i] = A::one() / A::from(self.num_actions).unwrap();
+                self.strategy[i] = A::one() / A::from(self.actions.len()).unwrap();
             }
         }
-        self.strategy.view()
+        &self.strategy
     }
 
     pub fn update_regret_sum(&mut self, action: usize, value: A) {
-        self.regret_sum[action] = value;
+        let i = self.entry(action);
+        self.regret_sum[i] = value;
     }
 
     pub fn get_regret_sum(&self, action: usize) -> A {
-        self.regret_sum[action]
+        self.index(action)
+            .map_or(A::zero(), |i| self.regret_sum[i])
     }
 
     pub fn get_strategy_sum(&self, action: usize) -> A {
-        self.strategy_sum[action]
+        self.index(action)
+            .map_or(A::zero(), |i| self.strategy_sum[i])
     }
 
     pub fn update_strategy_sum(&mut self, action: usize, value: A) {
-        self.strategy_sum[action] = value;
+        let i = self.entry(action);
+        self.strategy_sum[i] = value;
     }
 
     /// Updates the current strategy weighted by the probabilitiy of reaching
-    /// this state as well as all available actions in this state
-    pub fn update_strategy_sums(&mut self, prev_strategy: ArrayView1<A>, realization_weight: A) {
-        for a in 0..self.num_actions {
-            self.strategy_sum[a] += realization_weight * prev_strategy[a];
+    /// this state. The strategy lines up with `actions()`
+    pub fn update_strategy_sums(&mut self, prev_strategy: &[A], realization_weight: A) {
+        for (sum, &prob) in self.strategy_sum.iter_mut().zip(prev_strategy.iter()) {
+            *sum += realization_weight * prob;
+        }
+    }
+
+    /// Updates the regret sums using the reach probability of this node. The utility of each
+    /// action lines up with `actions()`
+    pub fn update_regrets(&mut self, reach_weight: A, state_utility: A, utility: &[A]) {
+        for (regret_sum, &action_utility) in self.regret_sum.iter_mut().zip(utility.iter()) {
+            *regret_sum += (action_utility - state_utility) * reach_weight;
+        }
+    }
+
+    /// Clamps all of the cumulative regrets to be non-negative (regret-matching+)
+    pub fn clamp_regrets(&mut self) {
+        for r in self.regret_sum.iter_mut() {
+            if *r < A::zero() {
+                *r = A::zero();
+            }
+        }
+    }
+
+    /// Scales the cumulative regrets. Positive and negative regrets are discounted separately
+    pub fn discount_regrets(&mut self, positive_factor: A, negative_factor: A) {
+        for r in self.regret_sum.iter_mut() {
+            if *r > A::zero() {
+                *r *= positive_factor;
+            } else {
+                *r *= negative_factor;
+            }
         }
     }
 
-    /// Updates the regret sums using the reach probability of this node
-    pub fn update_regrets(
-        &mut self,
-        reach_weight: A,
-        state_utility: A,
-        utility: Array1<A>,
-        available_actions: &[usize],
-    ) {
-        for &a in available_actions {
-            let regret = utility[a] - state_utility;
-            self.regret_sum[a] += regret * reach_weight;
+    /// Scales the cumulative strategy sums by the provided factor
+    pub fn discount_strategy_sum(&mut self, factor: A) {
+        for s in self.strategy_sum.iter_mut() {
+            *s *= factor;
         }
     }
 
+    /// Returns the most recently computed strategy, lined up with `actions()`
+    pub fn current_strategy(&self) -> &[A] {
+        &self.strategy
+    }
+
+    /// Returns the learned baseline of the player, lined up with `actions()`, if the player has
+    /// one
+    pub fn baseline(&self, player: usize) -> Option<&[A]> {
+        self.baselines
+            .get(player)
+            .filter(|b| !b.is_empty())
+            .map(|b| b.as_slice())
+    }
+
+    /// Moves the player's baseline of the action towards the provided value, where alpha is the
+    /// weight given to the new value
+    pub fn update_baseline(&mut self, player: usize, action: usize, value: A, alpha: A) {
+        let i = self.entry(action);
+        if self.baselines.len() <= player {
+            self.baselines.resize(player + 1, Vec::new());
+        }
+        let baseline = &mut self.baselines[player];
+        if baseline.is_empty() {
+            baseline.resize(self.actions.len(), A::zero());
+        }
+        baseline[i] = (A::one() - alpha) * baseline[i] + alpha * value;
+    }
+
     /// Returns the strategy score for the provided action
     pub fn get_strategy_for_action(&self, action: usize) -> A {
-        self.strategy[action]
+        self.index(action)
+            .map_or(A::zero(), |i| self.strategy[i])
     }
 
     /// Greedily samples the most likely action given the state
     pub fn sample_action_greedy(&self) -> usize {
-        let strat = self.strategy.argmax().unwrap();
-        strat
-    }
-
-    /// Gets the average strategy to be played in this state
-    pub fn get_average_strategy(&self) -> Array1<A> {
-        let mut avg_strategy = Array1::zeros(self.num_actions);
-        let normalizing_sum = self.strategy_sum.sum();
-        for i in 0..self.num_actions {
-            if normalizing_sum > A::zero() {
-                avg_strategy[i] = self.strategy_sum[i] / normalizing_sum;
-            } else {
-                // The average strategy is split uniformly across all actions in this case
-                avg_strategy[i] = A::one() / A::from(self.num_actions).unwrap();
+        let mut best = 0;
+        for i in 1..self.actions.len() {
+            if self.strategy[i] > self.strategy[best] {
+                best = i;
             }
         }
-        avg_strategy
+        self.actions[best]
+    }
+
+    /// Gets the average strategy to be played in this state, lined up with `actions()`
+    pub fn get_average_strategy(&self) -> Vec<A> {
+        let normalizing_sum = self
+            .strategy_sum
+            .iter()
+            .fold(A::zero(), |sum, &s| sum + s);
+        self.strategy_sum
+            .iter()
+            .map(|&s| {
+                if normalizing_sum > A::zero() {
+                    s / normalizing_sum
+                } else {
+                    // The average strategy is split uniformly across the stored actions in this case
+                    A::one() / A::from(self.actions.len()).unwrap()
+                }
+            })
+            .collect()
+    }
+}
+
+impl<A> StateNode<A> {
+    /// Number of bytes used by the node, including its entries and learned baselines
+    pub fn memory_bytes(&self) -> usize {
+        size_of::<Self>()
+            + self.actions.capacity() * size_of::<usize>()
+            + (self.regret_sum.capacity() + self.strategy.capacity() + self.strategy_sum.capacity())
+                * size_of::<A>()
+            + self.baselines.capacity() * size_of::<Vec<A>>()
+            + self
+                .baselines
+                .iter()
+                .map(|b| b.capacity() * size_of::<A>())
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StateNode;
+
+    #[test]
+    fn test_sparse_entries() {
+        let mut node = StateNode::<f32>::with_actions(1000, &[700, 3, 42]);
+        assert_eq!(node.actions(), &[3, 42, 700]);
+
+        node.update_regret_sum(42, 3.0);
+        node.update_regret_sum(700, 1.0);
+        assert_eq!(node.compute_strategy(), &[0.0, 0.75, 0.25]);
+        assert_eq!(node.sample_action_greedy(), 42);
+
+        // Actions without an entry are added as they are updated
+        assert_eq!(node.get_strategy_sum(5), 0.0);
+        node.update_strategy_sum(5, 2.0);
+        node.update_strategy_sum(3, 2.0);
+        assert_eq!(node.actions(), &[3, 5, 42, 700]);
+        assert_eq!(node.get_average_strategy(), vec![0.5, 0.5, 0.0, 0.0]);
+
+        // Baselines follow the entries of the actions they belong to
+        assert_eq!(node.baseline(1), None);
+        node.update_baseline(1, 42, 2.0, 0.5);
+        node.update_strategy_sum(4, 1.0);
+        assert_eq!(node.baseline(0), None);
+        assert_eq!(node.baseline(1), Some(&[0.0, 0.0, 0.0, 1.0, 0.0][..]));
+
+        let dense = StateNode::<f32>::new(1000);
+        assert!(node.memory_bytes() * 50 < dense.memory_bytes());
     }
 }
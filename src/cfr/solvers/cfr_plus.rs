@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use ndarray::NdFloat;
+use ndarray_rand::rand_distr::num_traits::Zero;
+use rand::rngs::StdRng;
+
+use crate::cfr::node::StateNode;
+use crate::cfr::state::GameState;
+
+use super::{LoadStrategies, Solver, SolverInfo};
+
+/// Training policy that uses CFR+. Like vanilla CFR this traverses the entire game tree, but
+/// cumulative regrets are clamped at zero at the end of every update (regret-matching+) and the
+/// average strategy is weighted linearly by the iteration it was accumulated on. Players are
+/// expected to be updated in alternation, which is how the trainer already calls into the solvers.
+/// Based on https://arxiv.org/abs/1407.5042
+pub struct CfrPlusSolver<S: GameState, A> {
+    /// Strategies in each game state
+    strategies: HashMap<S::Key, StateNode<A>>,
+    /// Number of valid actions in the entire game
+    num_actions: usize,
+    /// Information sets that have already been visited during the current update
+    visited: HashSet<S::Key>,
+    _a: PhantomData<A>,
+}
+
+impl<S: GameState, A> CfrPlusSolver<S, A>
+where
+    A: NdFloat + Zero,
+{
+    pub fn new(num_actions: usize) -> Self {
+        Self {
+            strategies: HashMap::new(),
+            num_actions,
+            visited: HashSet::new(),
+            _a: PhantomData,
+        }
+    }
+
+    /// CFR+
+    /// Params:
+    ///     curr_state: Current game state
+    ///     player: The index of the player to update a strategy for
+    ///     reach_player: The probability of reaching the current state if the player always selected actions leading to this node
+    ///     reach_other: The probability of reaching the current state if all other players except our target player selected actions leading to this node
+    ///     weight: Weight applied to the average strategy contribution of this iteration
+    /// Returns the expected payoff of the current player at this node
+    fn cfr_plus(
+        &mut self,
+        curr_state: &S,
+        player: usize,
+        reach_player: A,
+        reach_other: A,
+        weight: A,
+    ) -> A {
+        if curr_state.is_terminal() {
+            return A::from(curr_state.get_reward(player)).unwrap();
+        }
+
+        // Chance nodes are enumerated and weighted by the probability of each outcome
+        if curr_state.is_chance() {
+            let mut node_utility = A::zero();
+            for (outcome, prob) in curr_state.chance_outcomes() {
+                let prob = A::from(prob).unwrap();
+                let next_state = curr_state.apply_chance_outcome(outcome);
+                node_utility += prob
+                    * self.cfr_plus(
+                        &next_state,
+                        player,
+                        reach_player,
+                        reach_other * prob,
+                        weight,
+                    );
+            }
+            return node_utility;
+        }
+
+        // Strategies are keyed by what the acting player can observe
+        let active_player = curr_state.active_player();
+        let state_key = curr_state.information_set_key(active_player);
+        if !self.strategies.contains_key(&state_key) {
+            let node = StateNode::with_actions(self.num_actions, &curr_state.valid_actions());
+            self.strategies
+                .insert(curr_state.information_set_key(active_player), node);
+        }
+
+        // The strategy is only recomputed on the first visit of an update so that every history
+        // in the information set is evaluated against the same strategy
+        let first_visit = self
+            .visited
+            .insert(curr_state.information_set_key(active_player));
+        let node = self.strategies.get_mut(&state_key).unwrap();
+        let strategy = if first_visit {
+            node.compute_strategy().to_vec()
+        } else {
+            node.current_strategy().to_vec()
+        };
+        let actions = node.actions().to_vec();
+
+        let is_player = curr_state.active_player() == player;
+        let mut utility = vec![A::zero(); actions.len()];
+        let mut node_utility = A::zero();
+        for (i, &a) in actions.iter().enumerate() {
+            let next_state = curr_state.next_state(a).unwrap();
+            utility[i] = if is_player {
+                self.cfr_plus(
+                    &next_state,
+                    player,
+                    reach_player * strategy[i],
+                    reach_other,
+                    weight,
+                )
+            } else {
+                self.cfr_plus(
+                    &next_state,
+                    player,
+                    reach_player,
+                    reach_other * strategy[i],
+                    weight,
+                )
+            };
+            node_utility += strategy[i] * utility[i];
+        }
+
+        if is_player {
+            let node = self.strategies.get_mut(&state_key).unwrap();
+            node.update_regrets(reach_other, node_utility, &utility);
+            node.update_strategy_sums(&strategy, reach_player * weight);
+        }
+
+        node_utility
+    }
+}
+
+impl<S: GameState, A> Solver<S, A> for CfrPlusSolver<S, A>
+where
+    A: NdFloat + Zero,
+{
+    fn update_player_strategy(
+        &mut self,
+        initial_state: &S,
+        player: usize,
+        iteration: usize,
+        _rng: &mut StdRng,
+    ) -> A {
+        let weight = A::from(iteration).unwrap();
+        self.visited.clear();
+        let utility = self.cfr_plus(initial_state, player, A::one(), A::one(), weight);
+        // Regrets are only clamped once every history of the information set has added its
+        // regret, so negative regrets can cancel out positive ones from the same iteration
+        for key in self.visited.iter() {
+            self.strategies.get_mut(key).unwrap().clamp_regrets();
+        }
+        utility
+    }
+
+    fn seen_states(&self) -> usize {
+        self.strategies.len()
+    }
+
+    fn strategies(&self) -> &HashMap<S::Key, StateNode<A>> {
+        &self.strategies
+    }
+}
+
+impl<S: GameState, A> SolverInfo for CfrPlusSolver<S, A> {
+    fn name(&self) -> String {
+        "cfr_plus".to_string()
+    }
+
+    fn params(&self) -> String {
+        String::new()
+    }
+}
+
+impl<S: GameState, A> LoadStrategies<S::Key, A> for CfrPlusSolver<S, A>
+where
+    A: NdFloat + Zero,
+{
+    fn load_strategies(&mut self, strategies: HashMap<S::Key, StateNode<A>>) {
+        self.strategies = strategies;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfr::solvers::tests::{assert_converges_on_kuhn, first_guess_update};
+
+    use super::CfrPlusSolver;
+
+    #[test]
+    fn test_histories_share_information_set_strategy() {
+        // Both coin flips are evaluated against the uniform strategy of the first iteration,
+        // rather than the second one seeing the regrets accumulated by the first
+        let node = first_guess_update(CfrPlusSolver::new(2));
+        assert_eq!(node.get_strategy_sum(0), 1.0);
+        assert_eq!(node.get_strategy_sum(1), 1.0);
+    }
+
+    #[test]
+    fn test_clamps_regrets_after_whole_update() {
+        // The first coin flip has regrets (0.5, -0.5) and the second (-0.5, 0.5). Clamping
+        // after each history would leave (0, 0.5) rather than letting them cancel out
+        let node = first_guess_update(CfrPlusSolver::new(2));
+        assert_eq!(node.get_regret_sum(0), 0.0);
+        assert_eq!(node.get_regret_sum(1), 0.0);
+    }
+
+    #[test]
+    fn test_converges_on_kuhn() {
+        assert_converges_on_kuhn(CfrPlusSolver::new(2), 1000, 5e-3);
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use ndarray::NdFloat;
+use ndarray_rand::rand_distr::num_traits::Zero;
+use rand::rngs::StdRng;
+
+use crate::cfr::node::StateNode;
+use crate::cfr::state::GameState;
+
+use super::{LoadStrategies, Solver, SolverInfo};
+
+/// Training policy that uses Discounted CFR (DCFR). On iteration t, cumulative positive regrets
+/// are scaled by t^alpha / (t^alpha + 1), negative regrets by t^beta / (t^beta + 1) and the
+/// cumulative strategy by (t / (t + 1))^gamma.
+///
+/// Instead of sweeping over every node at the end of an iteration, the discount from the previous
+/// iteration is applied lazily the first time a node is visited in an iteration. Since the whole
+/// tree is traversed on every iteration this is equivalent to discounting all nodes at once.
+/// Based on https://arxiv.org/abs/1809.04040
+pub struct DiscountedCfrSolver<S: GameState, A> {
+    /// Strategies in each game state
+    strategies: HashMap<S::Key, StateNode<A>>,
+    /// Number of valid actions in the entire game
+    num_actions: usize,
+    /// Exponent used to discount positive regrets
+    alpha: A,
+    /// Exponent used to discount negative regrets
+    beta: A,
+    /// Exponent used to discount contributions to the average strategy
+    gamma: A,
+    /// Information sets that have already been visited (and discounted) during the current update
+    visited: HashSet<S::Key>,
+    _a: PhantomData<A>,
+}
+
+impl<S: GameState, A> DiscountedCfrSolver<S, A>
+where
+    A: NdFloat + Zero,
+{
+    pub fn new(num_actions: usize, alpha: A, beta: A, gamma: A) -> Self {
+        Self {
+            strategies: HashMap::new(),
+            num_actions,
+            alpha,
+            beta,
+            gamma,
+            visited: HashSet::new(),
+            _a: PhantomData,
+        }
+    }
+
+    /// Computes the (positive regret, negative regret, strategy) discounts accumulated at the end
+    /// of the iteration prior to the provided one. Iterations are 1-based, and iteration 0 is
+    /// treated as the first one
+    fn discount_factors(&self, iteration: usize) -> (A, A, A) {
+        let iteration = iteration.max(1);
+        let prev = A::from(iteration - 1).unwrap();
+        let curr = A::from(iteration).unwrap();
+        let pos = prev.powf(self.alpha) / (prev.powf(self.alpha) + A::one());
+        let neg = prev.powf(self.beta) / (prev.powf(self.beta) + A::one());
+        let strat = (prev / curr).powf(self.gamma);
+        (pos, neg, strat)
+    }
+
+    /// Discounted CFR
+    /// Params:
+    ///     curr_state: Current game state
+    ///     player: The index of the player to update a strategy for
+    ///     reach_player: The probability of reaching the current state if the player always selected actions leading to this node
+    ///     reach_other: The probability of reaching the current state if all other players except our target player selected actions leading to this node
+    ///     discounts: Discount factors to apply to the regrets and strategy sums before updating them
+    /// Returns the expected payoff of the current player at this node
+    fn discounted_cfr(
+        &mut self,
+        curr_state: &S,
+        player: usize,
+        reach_player: A,
+        reach_other: A,
+        discounts: (A, A, A),
+    ) -> A {
+        if curr_state.is_terminal() {
+            return A::from(curr_state.get_reward(player)).unwrap();
+        }
+
+        // Chance nodes are enumerated and weighted by the probability of each outcome
+        if curr_state.is_chance() {
+            let mut node_utility = A::zero();
+            for (outcome, prob) in curr_state.chance_outcomes() {
+                let prob = A::from(prob).unwrap();
+                let next_state = curr_state.apply_chance_outcome(outcome);
+                node_utility += prob
+                    * self.discounted_cfr(
+                        &next_state,
+                        player,
+                        reach_player,
+                        reach_other * prob,
+                        discounts,
+                    );
+            }
+            return node_utility;
+        }
+
+        // Strategies are keyed by what the acting player can observe
+        let active_player = curr_state.active_player();
+        let state_key = curr_state.information_set_key(active_player);
+        if !self.strategies.contains_key(&state_key) {
+            let node = StateNode::with_actions(self.num_actions, &curr_state.valid_actions());
+            self.strategies
+                .insert(curr_state.information_set_key(active_player), node);
+        }
+
+        // The strategy is only recomputed on the first visit of an update so that every history
+        // in the information set is evaluated against the same strategy
+        let first_visit = self
+            .visited
+            .insert(curr_state.information_set_key(active_player));
+        let node = self.strategies.get_mut(&state_key).unwrap();
+        let strategy = if first_visit {
+            node.compute_strategy().to_vec()
+        } else {
+            node.current_strategy().to_vec()
+        };
+        let actions = node.actions().to_vec();
+
+        // Apply the discount from the previous iteration before any new regrets are added
+        let is_player = curr_state.active_player() == player;
+        if is_player && first_visit {
+            let (pos, neg, strat) = discounts;
+            node.discount_regrets(pos, neg);
+            node.discount_strategy_sum(strat);
+        }
+
+        let mut utility = vec![A::zero(); actions.len()];
+        let mut node_utility = A::zero();
+        for (i, &a) in actions.iter().enumerate() {
+            let next_state = curr_state.next_state(a).unwrap();
+            utility[i] = if is_player {
+                self.discounted_cfr(
+                    &next_state,
+                    player,
+                    reach_player * strategy[i],
+                    reach_other,
+                    discounts,
+                )
+            } else {
+                self.discounted_cfr(
+                    &next_state,
+                    player,
+                    reach_player,
+                    reach_other * strategy[i],
+                    discounts,
+                )
+            };
+            node_utility += strategy[i] * utility[i];
+        }
+
+        if is_player {
+            let node = self.strategies.get_mut(&state_key).unwrap();
+            node.update_regrets(reach_other, node_utility, &utility);
+            node.update_strategy_sums(&strategy, reach_player);
+        }
+
+        node_utility
+    }
+}
+
+impl<S: GameState, A> Solver<S, A> for DiscountedCfrSolver<S, A>
+where
+    A: NdFloat + Zero,
+{
+    fn update_player_strategy(
+        &mut self,
+        initial_state: &S,
+        player: usize,
+        iteration: usize,
+        _rng: &mut StdRng,
+    ) -> A {
+        let discounts = self.discount_factors(iteration);
+        self.visited.clear();
+        self.discounted_cfr(initial_state, player, A::one(), A::one(), discounts)
+    }
+
+    fn seen_states(&self) -> usize {
+        self.strategies.len()
+    }
+
+    fn strategies(&self) -> &HashMap<S::Key, StateNode<A>> {
+        &self.strategies
+    }
+}
+
+impl<S: GameState, A> SolverInfo for DiscountedCfrSolver<S, A>
+where
+    A: NdFloat,
+{
+    fn name(&self) -> String {
+        "discounted".to_string()
+    }
+
+    fn params(&self) -> String {
+        format!("alpha={:?}, beta={:?}, gamma={:?}", self.alpha, self.beta, self.gamma)
+    }
+}
+
+impl<S: GameState, A> LoadStrategies<S::Key, A> for DiscountedCfrSolver<S, A>
+where
+    A: NdFloat + Zero,
+{
+    fn load_strategies(&mut self, strategies: HashMap<S::Key, StateNode<A>>) {
+        self.strategies = strategies;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfr::solvers::tests::{assert_converges_on_kuhn, first_guess_update};
+    use crate::kuhn::KuhnState;
+
+    use super::DiscountedCfrSolver;
+
+    #[test]
+    fn test_histories_share_information_set_strategy() {
+        // Both coin flips are evaluated against the uniform strategy of the first iteration,
+        // rather than the second one seeing the regrets accumulated by the first
+        let node = first_guess_update(DiscountedCfrSolver::new(2, 1.5, 0.0, 2.0));
+        assert_eq!(node.get_strategy_sum(0), 1.0);
+        assert_eq!(node.get_strategy_sum(1), 1.0);
+        assert_eq!(node.get_regret_sum(0), 0.0);
+        assert_eq!(node.get_regret_sum(1), 0.0);
+    }
+
+    #[test]
+    fn test_discounts_first_iteration() {
+        let solver = DiscountedCfrSolver::<KuhnState, f64>::new(2, 1.5, 0.0, 2.0);
+        assert_eq!(solver.discount_factors(0), solver.discount_factors(1));
+        assert_eq!(solver.discount_factors(1), (0.0, 0.5, 0.0));
+        assert_eq!(solver.discount_factors(2), (0.5, 0.5, 0.25));
+    }
+
+    #[test]
+    fn test_converges_on_kuhn() {
+        assert_converges_on_kuhn(DiscountedCfrSolver::new(2, 1.5, 0.0, 2.0), 1000, 5e-3);
+    }
+}
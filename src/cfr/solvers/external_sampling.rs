@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use ndarray::NdFloat;
+use ndarray_rand::rand_distr::num_traits::Zero;
+use ndarray_rand::rand_distr::uniform::SampleUniform;
+use ndarray_rand::rand_distr::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+
+use crate::cfr::config::SolverConfig;
+use crate::cfr::node::StateNode;
+use crate::cfr::state::GameState;
+use crate::error::Result;
+
+use super::{sample_chance_outcome, LoadStrategies, Solver, SolverInfo};
+
+/// Training policy that uses the external sampling variant of CFR. Every action of the player
+/// being updated is traversed while a single action is sampled for all other players. This has
+/// much lower variance per iteration than outcome sampling at the cost of a larger traversal.
+/// Implementation is based off of
+/// https://github.com/deepmind/open_spiel/blob/master/open_spiel/algorithms/external_sampling_mccfr.cc
+pub struct ExternalSamplingSolver<S: GameState, A> {
+    /// Strategies in each game state
+    strategies: HashMap<S::Key, StateNode<A>>,
+    /// Number of valid actions in the entire game
+    num_actions: usize,
+    /// Hyperparameters of the solver. Only the averaging mode applies to external sampling
+    config: SolverConfig,
+    /// Weight of contributions to the average strategy on the current iteration
+    averaging_weight: A,
+    _a: PhantomData<A>,
+}
+
+impl<S: GameState, A> ExternalSamplingSolver<S, A>
+where
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
+{
+    pub fn new(num_actions: usize) -> Self {
+        Self::with_config(num_actions, SolverConfig::default()).expect("Default config is valid")
+    }
+
+    /// Creates a solver with the provided hyperparameters. Fails if any of them is invalid
+    pub fn with_config(num_actions: usize, config: SolverConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            strategies: HashMap::new(),
+            num_actions,
+            config,
+            averaging_weight: A::one(),
+            _a: PhantomData,
+        })
+    }
+
+    /// External Sampling Monte-Carlo CFR
+    /// Params:
+    ///     curr_state: Current game state
+    ///     player: The index of the player to update a strategy for
+    ///     rng: Random number generator used to sample the other players and chance
+    /// Returns the sampled expected payoff of the current player at this node
+    fn external_sampling_cfr(&mut self, curr_state: &S, player: usize, rng: &mut StdRng) -> A {
+        if curr_state.is_terminal() {
+            return A::from(curr_state.get_reward(player)).unwrap();
+        }
+
+        // Chance nodes are sampled just like the other players
+        if curr_state.is_chance() {
+            let (outcome, _) = sample_chance_outcome(curr_state, rng);
+            let next_state = curr_state.apply_chance_outcome(outcome);
+            return self.external_sampling_cfr(&next_state, player, rng);
+        }
+
+        // Strategies are keyed by what the acting player can observe
+        let active_player = curr_state.active_player();
+        let state_key = curr_state.information_set_key(active_player);
+        if !self.strategies.contains_key(&state_key) {
+            let node = StateNode::with_actions(self.num_actions, &curr_state.valid_actions());
+            self.strategies
+                .insert(curr_state.information_set_key(active_player), node);
+        }
+
+        let node = self.strategies.get_mut(&state_key).unwrap();
+        let strategy = node.compute_strategy().to_vec();
+        let actions = node.actions().to_vec();
+
+        if curr_state.active_player() == player {
+            // Explore every action of the player being updated
+            let mut utility = vec![A::zero(); actions.len()];
+            let mut node_utility = A::zero();
+            for (i, &a) in actions.iter().enumerate() {
+                let next_state = curr_state.next_state(a).unwrap();
+                utility[i] = self.external_sampling_cfr(&next_state, player, rng);
+                node_utility += strategy[i] * utility[i];
+            }
+
+            // The sampled counterfactual values are already weighted by the sampling
+            // probability of the other players so the regrets are not reach weighted
+            let node = self.strategies.get_mut(&state_key).unwrap();
+            node.update_regrets(A::one(), node_utility, &utility);
+            node_utility
+        } else {
+            // Sample a single action for every other player and accumulate their average strategy
+            let dist = WeightedIndex::new(strategy.iter()).unwrap();
+            let selected_action = actions[dist.sample(rng)];
+
+            self.strategies
+                .get_mut(&state_key)
+                .unwrap()
+                .update_strategy_sums(&strategy, self.averaging_weight);
+
+            let next_state = curr_state.next_state(selected_action).unwrap();
+            self.external_sampling_cfr(&next_state, player, rng)
+        }
+    }
+}
+
+impl<S: GameState, A> Solver<S, A> for ExternalSamplingSolver<S, A>
+where
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
+{
+    fn update_player_strategy(
+        &mut self,
+        initial_state: &S,
+        player: usize,
+        iteration: usize,
+        rng: &mut StdRng,
+    ) -> A {
+        self.averaging_weight = A::from(self.config.averaging_weight(iteration)).unwrap();
+        self.external_sampling_cfr(initial_state, player, rng)
+    }
+
+    fn seen_states(&self) -> usize {
+        self.strategies.len()
+    }
+
+    fn strategies(&self) -> &HashMap<S::Key, StateNode<A>> {
+        &self.strategies
+    }
+}
+
+impl<S: GameState, A> SolverInfo for ExternalSamplingSolver<S, A> {
+    fn name(&self) -> String {
+        "external_sampling".to_string()
+    }
+
+    fn params(&self) -> String {
+        self.config.external_sampling_params()
+    }
+}
+
+impl<S: GameState, A> LoadStrategies<S::Key, A> for ExternalSamplingSolver<S, A>
+where
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
+{
+    fn load_strategies(&mut self, strategies: HashMap<S::Key, StateNode<A>>) {
+        self.strategies = strategies;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfr::solvers::tests::assert_converges_on_kuhn;
+
+    use super::ExternalSamplingSolver;
+
+    #[test]
+    fn test_converges_on_kuhn() {
+        assert_converges_on_kuhn(ExternalSamplingSolver::new(2), 20000, 2e-2);
+    }
+}
@@ -1,4 +1,243 @@
+mod cfr_plus;
+mod discounted;
+mod external_sampling;
 mod outcome_sampling;
+mod parallel_outcome_sampling;
+mod vanilla;
 
+use std::collections::HashMap;
 
-pub use self::outcome_sampling::OutcomeSamplingSolver;
\ No newline at end of file
+use ndarray_rand::rand_distr::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+
+use super::node::StateNode;
+use super::state::GameState;
+use super::store::StoreStats;
+
+pub use self::cfr_plus::CfrPlusSolver;
+pub use self::discounted::DiscountedCfrSolver;
+pub use self::external_sampling::ExternalSamplingSolver;
+pub use self::outcome_sampling::{Baseline, OutcomeSamplingSolver};
+pub use self::parallel_outcome_sampling::ParallelOutcomeSamplingSolver;
+pub use self::vanilla::VanillaCfrSolver;
+
+/// Describes a solver, so checkpoints record what trained them and are only resumed by the
+/// same solver
+pub trait SolverInfo {
+    /// Name of the CFR variant, recorded in checkpoints
+    fn name(&self) -> String;
+    /// Hyperparameters that change what the solver learns, recorded in checkpoints. The format
+    /// must stay the same between versions so existing checkpoints can still be resumed
+    fn params(&self) -> String;
+}
+
+/// Common interface shared by every variant of CFR so the trainer does not need to know
+/// which algorithm it is running
+pub trait Solver<S: GameState, A>: SolverInfo {
+    /// Runs a single iteration of the solver from the initial state, updating the strategy
+    /// of the provided player. The iteration is 1-based. All sampling is done with the provided
+    /// random number generator. Returns the (estimated) utility of the player
+    fn update_player_strategy(
+        &mut self,
+        initial_state: &S,
+        player: usize,
+        iteration: usize,
+        rng: &mut StdRng,
+    ) -> A;
+    /// Number of unique states the solver has created a strategy for
+    fn seen_states(&self) -> usize;
+    /// Gets the strategies learned for each state
+    fn strategies(&self) -> &HashMap<S::Key, StateNode<A>>;
+    /// Memory usage and eviction statistics of the strategy store, if the solver bounds the
+    /// memory of its strategies
+    fn store_stats(&self) -> Option<StoreStats> {
+        None
+    }
+}
+
+/// Solver whose iterations can run concurrently from several threads against a shared strategy
+/// store. Since the strategies are updated in place by every thread, they can only be read as a
+/// snapshot
+pub trait ParallelSolver<S: GameState, A>: SolverInfo + Sync {
+    /// Runs a single iteration of the solver from the initial state, updating the strategy
+    /// of the provided player. The iteration is 1-based. All sampling is done with the provided
+    /// random number generator. Returns the (estimated) utility of the player
+    fn update_player_strategy(
+        &self,
+        initial_state: &S,
+        player: usize,
+        iteration: usize,
+        rng: &mut StdRng,
+    ) -> A;
+    /// Number of unique states the solver has created a strategy for
+    fn seen_states(&self) -> usize;
+    /// Copies the strategies learned for each state so far
+    fn strategies_snapshot(&self) -> HashMap<S::Key, StateNode<A>>;
+    /// Rough estimate of the memory used by the strategies, in bytes
+    fn memory_bytes(&self) -> usize;
+}
+
+/// Solvers that can start from previously learned strategies, i.e. when resuming training from
+/// a checkpoint
+pub trait LoadStrategies<K, A> {
+    /// Replaces the strategies of the solver with the provided ones
+    fn load_strategies(&mut self, strategies: HashMap<K, StateNode<A>>);
+}
+
+/// Samples an outcome at a chance node according to the outcome probabilities.
+/// Returns the sampled outcome along with its probability
+fn sample_chance_outcome<S: GameState>(state: &S, rng: &mut StdRng) -> (usize, f32) {
+    let outcomes = state.chance_outcomes();
+    let dist = WeightedIndex::new(outcomes.iter().map(|(_, prob)| *prob)).unwrap();
+    outcomes[dist.sample(rng)]
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::cfr::eval;
+    use crate::cfr::node::StateNode;
+    use crate::cfr::state::{Game, GameState};
+    use crate::kuhn::{KuhnPoker, KuhnState};
+
+    use super::Solver;
+
+    /// Value of Kuhn poker for the first player when both players play an equilibrium
+    const KUHN_GAME_VALUE: f64 = -1.0 / 18.0;
+
+    /// Game where chance flips a coin that the guessing player cannot see. A correct guess wins
+    /// 1 and a wrong one loses 1, so the two histories in the guessing information set have
+    /// regrets of opposite signs. The second player never acts
+    #[derive(Debug, Clone)]
+    pub struct HiddenCoinState {
+        coin: Option<usize>,
+        guess: Option<usize>,
+    }
+
+    impl GameState for HiddenCoinState {
+        type Key = String;
+
+        fn active_player(&self) -> usize {
+            0
+        }
+
+        fn valid_actions(&self) -> Vec<usize> {
+            vec![0, 1]
+        }
+
+        fn state_key(&self) -> Self::Key {
+            format!("{:?}", self.coin)
+        }
+
+        fn information_set_key(&self, _player: usize) -> Self::Key {
+            String::new()
+        }
+
+        fn next_state(&self, action: usize) -> Option<Self> {
+            Some(Self {
+                coin: self.coin,
+                guess: Some(action),
+            })
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.guess.is_some()
+        }
+
+        fn is_chance(&self) -> bool {
+            self.coin.is_none()
+        }
+
+        fn chance_outcomes(&self) -> Vec<(usize, f32)> {
+            vec![(0, 0.5), (1, 0.5)]
+        }
+
+        fn apply_chance_outcome(&self, outcome: usize) -> Self {
+            Self {
+                coin: Some(outcome),
+                guess: None,
+            }
+        }
+
+        fn get_reward(&self, player: usize) -> f32 {
+            let reward = if self.coin == self.guess { 1.0 } else { -1.0 };
+            if player == 0 {
+                reward
+            } else {
+                -reward
+            }
+        }
+    }
+
+    pub struct HiddenCoin;
+
+    impl Game for HiddenCoin {
+        type State = HiddenCoinState;
+
+        fn num_players(&self) -> usize {
+            2
+        }
+
+        fn num_actions(&self) -> usize {
+            2
+        }
+
+        fn start(&self) -> Self::State {
+            HiddenCoinState {
+                coin: None,
+                guess: None,
+            }
+        }
+
+        fn reset(&mut self) {}
+
+        fn name(&self) -> String {
+            "hidden_coin".to_string()
+        }
+    }
+
+    /// Runs the first update of the guessing player and returns the node of the guess
+    pub fn first_guess_update<S: Solver<HiddenCoinState, f64>>(mut solver: S) -> StateNode<f64> {
+        let mut rng = StdRng::seed_from_u64(0);
+        solver.update_player_strategy(&HiddenCoin.start(), 0, 1, &mut rng);
+        solver.strategies()[""].clone()
+    }
+
+    /// Checks that the strategies are within the tolerance of a Kuhn poker equilibrium
+    pub fn assert_kuhn_equilibrium(strategies: &HashMap<String, StateNode<f64>>, tolerance: f64) {
+        let report = eval::exploitability(&KuhnPoker::new(), strategies);
+        assert!(
+            (report.values[0] - KUHN_GAME_VALUE).abs() < tolerance,
+            "Expected game value of {} but got {}",
+            KUHN_GAME_VALUE,
+            report.values[0]
+        );
+        assert!(
+            report.exploitability < tolerance,
+            "Exploitability too high: {}",
+            report.exploitability
+        );
+    }
+
+    /// Trains the solver on Kuhn poker and checks that the average strategy converges to the
+    /// game value
+    pub fn assert_converges_on_kuhn<S: Solver<KuhnState, f64>>(
+        mut solver: S,
+        rounds: usize,
+        tolerance: f64,
+    ) {
+        let game = KuhnPoker::new();
+        let initial_state = game.start();
+        let mut rng = StdRng::seed_from_u64(0);
+        for i in 0..rounds {
+            for p in 0..game.num_players() {
+                solver.update_player_strategy(&initial_state, p, i + 1, &mut rng);
+            }
+        }
+        assert_kuhn_equilibrium(solver.strategies(), tolerance);
+    }
+}
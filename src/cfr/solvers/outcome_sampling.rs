@@ -1,220 +1,422 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::marker::PhantomData;
 
-use ndarray::{Array1, ArrayView1, NdFloat};
+use ndarray::NdFloat;
 use ndarray_rand::rand_distr::num_traits::Zero;
 use ndarray_rand::rand_distr::uniform::SampleUniform;
-use ndarray_rand::rand_distr::{Distribution, Uniform, WeightedIndex};
-use rand::prelude::SliceRandom;
+use ndarray_rand::rand_distr::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
+use crate::cfr::config::SolverConfig;
 use crate::cfr::node::StateNode;
 use crate::cfr::state::GameState;
+use crate::cfr::store::{NodeStore, StoreStats, StrategyStore};
+use crate::error::Result;
 
-const EPSILON: f32 = 0.6;
-const REACH_CLIP: f32 = 1e-12;
+use super::{sample_chance_outcome, LoadStrategies, Solver, SolverInfo};
+
+/// Baseline subtracted from the sampled values to reduce the variance of outcome sampling
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Baseline {
+    /// No baseline, which reduces to regular outcome sampling
+    Zero,
+    /// Baseline learned for every information set and action as an exponentially weighted
+    /// average of the sampled values. The value is the weight given to each new sample
+    Learned(f32),
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Baseline::Zero => write!(f, "zero"),
+            Baseline::Learned(alpha) => write!(f, "learned({})", alpha),
+        }
+    }
+}
 
 /// Training policy that uses the outcome sampling variant of CFR
 /// Implementation is based off of https://github.com/bakanaouji/cpp-cfr
 /// and https://github.com/deepmind/open_spiel/blob/master/open_spiel/algorithms/outcome_sampling_mccfr.cc
-pub struct OutcomeSamplingSolver<'a, S: GameState, A> {
-    /// Mutable reference to the strategies in each game state
-    strategies: &'a mut HashMap<S::Key, StateNode<A>>,
+/// Optionally uses the learned baselines from VR-MCCFR (https://arxiv.org/abs/1809.03057)
+pub struct OutcomeSamplingSolver<S: GameState, A> {
+    /// Strategies in each game state
+    strategies: StrategyStore<S::Key, A>,
     /// Number of valid actions in the entire game
     num_actions: usize,
+    /// Hyperparameters of the solver
+    config: SolverConfig,
     _a: PhantomData<A>,
 }
 
-impl<'a, S: GameState, A> OutcomeSamplingSolver<'a, S, A>
+impl<S: GameState, A> OutcomeSamplingSolver<S, A>
 where
     A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
 {
-    pub fn new(strategies: &'a mut HashMap<S::Key, StateNode<A>>, num_actions: usize) -> Self {
-        Self {
-            strategies,
+    pub fn new(num_actions: usize) -> Self {
+        Self::with_config(num_actions, SolverConfig::default()).expect("Default config is valid")
+    }
+
+    /// Creates a solver with the provided hyperparameters. Fails if any of them is invalid
+    pub fn with_config(num_actions: usize, config: SolverConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            strategies: StrategyStore::new(config.max_memory_bytes),
             num_actions,
+            config,
             _a: PhantomData,
+        })
+    }
+
+    /// Sets the type of baseline used to reduce the variance of the sampled values
+    pub fn set_baseline(&mut self, baseline: Baseline) {
+        self.config.baseline = baseline;
+    }
+}
+
+/// Everything an outcome sampling iteration needs that stays the same at every node
+pub(super) struct SamplingContext<'a, A> {
+    /// The index of the player to update a strategy for
+    pub player: usize,
+    /// Number of valid actions in the entire game
+    pub num_actions: usize,
+    /// Exploration probability of the player being updated
+    pub epsilon: A,
+    /// Weight of contributions to the average strategy
+    pub averaging_weight: A,
+    /// Lower bound of the sampling probability used to weight the regrets
+    pub reach_clip: A,
+    /// Type of baseline used to reduce the variance of the sampled values
+    pub baseline: Baseline,
+    /// Random number generator used to sample actions and chance outcomes
+    pub rng: &'a mut StdRng,
+}
+
+impl<'a, A: NdFloat> SamplingContext<'a, A> {
+    /// Creates the context of an iteration of the provided solver config
+    pub fn new(
+        config: &SolverConfig,
+        num_actions: usize,
+        player: usize,
+        iteration: usize,
+        rng: &'a mut StdRng,
+    ) -> Self {
+        Self {
+            player,
+            num_actions,
+            epsilon: A::from(config.epsilon_at(iteration)).unwrap(),
+            averaging_weight: A::from(config.averaging_weight(iteration)).unwrap(),
+            reach_clip: A::from(config.reach_clip).unwrap(),
+            baseline: config.baseline,
+            rng,
         }
     }
-    pub fn update_player_strategy(&mut self, initial_state: &S, player: usize) -> A {
-        let utility = self.outcome_sampling_cfr(
-            initial_state,
+}
+
+/// Chance Sampling Monte-Carlo CFR. Runs against any strategy store, so the sequential and
+/// parallel solvers share it
+/// Params:
+///     nodes: Strategies of the visited states
+///     ctx: Parts of the iteration that are the same at every node
+///     curr_state: Current game state
+///     reach_player: The probability of reaching the current state if the player always selected actions leading to this node
+///     reach_other: The proabbility of reaching the current state if all other players except our target player selected actions leading to this node
+///     reach_chance: Probability of reaching state if both other players and chance nodes choses actions leading to the terminal node
+/// Returns the expected payoff of the current player and the probability of actually reaching this node due to other upstream chance nodes
+pub(super) fn outcome_sampling_cfr<S, A, N>(
+    nodes: &mut N,
+    ctx: &mut SamplingContext<A>,
+    curr_state: &S,
+    reach_player: A,
+    reach_other: A,
+    reach_chance: A,
+) -> A
+where
+    S: GameState,
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
+    N: NodeStore<S::Key, A>,
+{
+    let player = ctx.player;
+    // Upon a terminal state, just return the reward for the current player
+    if curr_state.is_terminal() {
+        return A::from(curr_state.get_reward(player)).unwrap();
+    }
+
+    // Chance outcomes are sampled from their true distribution, so the chance probability
+    // contributes to both the reach of the other players and the sampling probability
+    if curr_state.is_chance() {
+        let (outcome, prob) = sample_chance_outcome(curr_state, ctx.rng);
+        let prob = A::from(prob).unwrap();
+        let next_state = curr_state.apply_chance_outcome(outcome);
+        return outcome_sampling_cfr(
+            nodes,
+            ctx,
+            &next_state,
+            reach_player,
+            reach_other * prob,
+            reach_chance * prob,
+        );
+    }
+
+    // Strategies are keyed by what the acting player can observe
+    let active_player = curr_state.active_player();
+    let state_key = curr_state.information_set_key(active_player);
+    // Compute the strategy for the current node, creating an entry for it if necessary
+    let (strategy, actions) = nodes.visit_node(
+        &state_key,
+        || StateNode::with_actions(ctx.num_actions, &curr_state.valid_actions()),
+        |node| (node.compute_strategy().to_vec(), node.actions().to_vec()),
+    );
+
+    // Sample a policy and take a randomly weighted action from that policy
+    // Only the player being updated explores
+    let is_player = active_player == player;
+    let epsilon = if is_player { ctx.epsilon } else { A::zero() };
+    let mut action_probs = sample_policy(&strategy, epsilon);
+    let selected_idx;
+    if let Ok(dist) = WeightedIndex::new(action_probs.iter()) {
+        selected_idx = dist.sample(ctx.rng);
+    } else {
+        // Weird edge case in scrabble where the probabilities diverge to 0
+        selected_idx = ctx.rng.gen_range(0..actions.len());
+        action_probs[selected_idx] = A::one() / A::from(actions.len()).unwrap();
+    }
+    let selected_action = actions[selected_idx];
+    // For the sampled action, recursively call the CFR method and update weights
+    let next_state = curr_state.next_state(selected_action).unwrap();
+    let (new_reach_player, new_reach_other) = if is_player {
+        (reach_player * strategy[selected_idx], reach_other)
+    } else {
+        (reach_player, reach_other * strategy[selected_idx])
+    };
+    let child_value = outcome_sampling_cfr(
+        nodes,
+        ctx,
+        &next_state,
+        new_reach_player,
+        new_reach_other,
+        reach_chance * action_probs[selected_idx],
+    );
+
+    // Estimate the value of each child action. Learning a baseline grows the node, which
+    // bounded stores count against their memory limit
+    let baseline = ctx.baseline;
+    let child_values = nodes.update_node(&state_key, |node| {
+        let child_values = estimate_child_values(
+            node,
+            baseline,
             player,
-            A::from(1.0).unwrap(),
-            A::from(1.0).unwrap(),
-            A::from(1.0).unwrap(),
+            selected_idx,
+            child_value,
+            &action_probs,
         );
-        utility
+        update_baseline(node, baseline, player, selected_action, child_value);
+        child_values
+    });
+
+    // Compute the value estimate for this node
+    let mut value_estimate = A::zero();
+    for (&prob, &value) in strategy.iter().zip(child_values.iter()) {
+        value_estimate += prob * value;
     }
 
-    pub fn seen_states(&self) -> usize {
-        self.strategies.len()
+    // Update regrets and average strategy for the player
+    if is_player {
+        // Clip the sampling probability so values of unlikely histories do not explode
+        let reach_chance = reach_chance.max(ctx.reach_clip);
+        let averaging_weight = ctx.averaging_weight;
+        nodes.update_node(&state_key, |node| {
+            // Recompute the strategy again using cumulative regrets from all downstream nodes
+            let updated_policy = node.compute_strategy().to_vec();
+
+            // The counterfactual values weight the sampled values by the reach of the other
+            // players and the chance that this node was actually sampled. Since we are already
+            // returning utilities from downstream recursive calls as they are multiplied by the
+            // chance of reaching that state, we dont need to deal with the tail call probability
+            node.update_regrets(reach_other / reach_chance, value_estimate, &child_values);
+
+            // Now we need to update the cumulative (average) strategy for each valid action
+            node.update_strategy_sums(
+                &updated_policy,
+                averaging_weight * reach_player / reach_chance,
+            );
+        });
     }
+    value_estimate
+}
+
+/// Samples a policy by mixing the strategy with a uniform distribution over the actions it
+/// covers, where epsilon is the probability of exploring
+fn sample_policy<A: NdFloat>(strategy: &[A], epsilon: A) -> Vec<A> {
+    debug_assert!(!strategy.is_empty(), "Must have at least 1 valid action");
+    let num_acts = A::from(strategy.len()).unwrap();
+    strategy
+        .iter()
+        .map(|&prob| (epsilon / num_acts) + (A::one() - epsilon) * prob)
+        .collect()
+}
 
-    pub fn strategies(&self) -> &HashMap<S::Key, StateNode<A>> {
-        &self.strategies
+/// Corrects the sampled value with the baseline. Unsampled actions use the baseline as
+/// their estimate, which keeps the estimate unbiased while reducing its variance
+fn baseline_corrected_value<A: NdFloat>(
+    action_idx: usize,
+    sampled_idx: usize,
+    value: A,
+    sample_prob: A,
+    baseline: A,
+) -> A {
+    if action_idx == sampled_idx {
+        baseline + (value - baseline) / sample_prob
+    } else {
+        baseline
     }
+}
 
-    /// Chance Sampling Monte-Carlo CFR
-    /// Params:
-    ///     game: Reference to the current game object
-    ///     state: Current game state
-    ///     player: The index of the player to update a strategy for
-    ///     reach_player: The probability of reaching the current state if the player always selected actions leading to this node
-    ///     reach_other: The proabbility of reaching the current state if all other players except our target player selected actions leading to this node
-    ///     reach_chance: Probability of reaching state if both other players and chance nodes choses actions leading to the terminal node
-    /// Returns the expected payoff of the current player and the probability of actually reaching this node due to other upstream chance nodes
-    fn outcome_sampling_cfr(
+/// Estimates the value of each action in the node from the value of the sampled one, using the
+/// player's learned baseline if there is one
+fn estimate_child_values<A: NdFloat>(
+    node: &StateNode<A>,
+    baseline: Baseline,
+    player: usize,
+    sampled_idx: usize,
+    value: A,
+    action_probs: &[A],
+) -> Vec<A> {
+    let learned = match baseline {
+        Baseline::Zero => None,
+        Baseline::Learned(_) => node.baseline(player),
+    };
+    action_probs
+        .iter()
+        .enumerate()
+        .map(|(i, &prob)| {
+            let baseline = learned.map_or(A::zero(), |b| b[i]);
+            baseline_corrected_value(i, sampled_idx, value, prob, baseline)
+        })
+        .collect()
+}
+
+/// Moves the player's baseline of the sampled action towards the value that was observed for it
+fn update_baseline<A: NdFloat>(
+    node: &mut StateNode<A>,
+    baseline: Baseline,
+    player: usize,
+    action: usize,
+    value: A,
+) {
+    if let Baseline::Learned(alpha) = baseline {
+        node.update_baseline(player, action, value, A::from(alpha).unwrap());
+    }
+}
+
+impl<S: GameState, A> Solver<S, A> for OutcomeSamplingSolver<S, A>
+where
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
+{
+    fn update_player_strategy(
         &mut self,
-        curr_state: &S,
+        initial_state: &S,
         player: usize,
-        reach_player: A,
-        reach_other: A,
-        reach_chance: A,
+        iteration: usize,
+        rng: &mut StdRng,
     ) -> A {
-        // Upon a terminal state, just return the reward for the current player
-        if curr_state.is_terminal() {
-            return A::from(curr_state.get_reward(player)).unwrap();
-        }
+        let mut ctx = SamplingContext::new(&self.config, self.num_actions, player, iteration, rng);
+        let utility = outcome_sampling_cfr(
+            &mut self.strategies,
+            &mut ctx,
+            initial_state,
+            A::one(),
+            A::one(),
+            A::one(),
+        );
+        // The learned baselines are part of the nodes, so they are evicted along with them
+        self.strategies.enforce_limit();
+        utility
+    }
 
-        let state_key = curr_state.state_key();
-        // If necessary, create an entry for the current state node
-        if !self.strategies.contains_key(&state_key) {
-            let node = StateNode::new(self.num_actions);
-            self.strategies.insert(curr_state.state_key(), node);
-        }
+    fn seen_states(&self) -> usize {
+        self.strategies.len()
+    }
 
-        // Compute the strategy for the current node
-        let strategy = self
-            .strategies
-            .get_mut(&state_key)
-            .unwrap()
-            .compute_strategy()
-            .to_owned();
-
-        // Sample a policy and take a randomly weighted action from that policy
-        let mut rng = rand::thread_rng();
-        let valid_actions = curr_state.valid_actions();
-        //assert!(valid_actions.len() > 0, "Must have at least 1 valid action");
-        let mut action_probs =
-            self.sample_policy(curr_state, player, strategy.view(), &valid_actions);
-        let selected_action;
-        if let Ok(dist) = WeightedIndex::new(action_probs.iter()) {
-            selected_action = dist.sample(&mut rng);
-        } else {
-            // Weird edge case in scrabble where the probabilities diverge to 0
-            selected_action = *valid_actions.choose(&mut rng).unwrap();
-            action_probs[selected_action] = A::one() / A::from(self.num_actions).unwrap();
-        }
-        // For the sampled action, recursively call the CFR method and update weights
-        let next_state = curr_state.next_state(selected_action).unwrap();
-        let new_reach_player = if player == curr_state.active_player() {
-            reach_player * strategy[selected_action]
-        } else {
-            reach_player
-        };
-        let new_reach_other = if player == curr_state.active_player() {
-            reach_other
-        } else {
-            reach_other * strategy[selected_action]
-        };
-        let new_reach_chance = reach_chance * action_probs[selected_action];
-        let child_value = self.outcome_sampling_cfr(
-            &next_state,
-            player,
-            new_reach_player,
-            new_reach_other,
-            new_reach_chance,
-        );
+    fn strategies(&self) -> &HashMap<S::Key, StateNode<A>> {
+        self.strategies.nodes()
+    }
 
-        // Estimate the value of each child action
-        let mut child_values = Array1::zeros(self.num_actions);
-        for &a in valid_actions.iter() {
-            child_values[a] =
-                self.baseline_corrected_value(a, selected_action, child_value, action_probs[a]);
-        }
+    fn store_stats(&self) -> Option<StoreStats> {
+        Some(self.strategies.stats())
+    }
+}
 
-        // Compute the value estimate for this node
-        let mut value_estimate = A::zero();
-        for &a in valid_actions.iter() {
-            value_estimate += strategy[a] * child_values[a];
-        }
+impl<S: GameState, A> SolverInfo for OutcomeSamplingSolver<S, A> {
+    fn name(&self) -> String {
+        "outcome_sampling".to_string()
+    }
 
-        // Update regrets and average strategy for the player
-        if curr_state.active_player() == player {
-            // Recompute the strategy again using cumulative regrets from all downstream nodes
-            let udpdated_policy = self
-                .strategies
-                .get_mut(&state_key)
-                .unwrap()
-                .compute_strategy()
-                .to_owned();
-            // Compute a counterfactual value using our current value, the reach of other players
-            // and the chance that this node was actually sampled
-            let cf_value = value_estimate * reach_other / reach_chance;
-
-            // Since we are already returning utilities from downstream recursive calls as they are multiplied
-            // by the chance of reaching that state, we dont need to deal with the tail call probability
-            for &a in valid_actions.iter() {
-                let cf_action_value = child_values[a] * reach_other / reach_chance;
-                let curr_regret = self.strategies.get(&state_key).unwrap().get_regret_sum(a);
-                self.strategies
-                    .get_mut(&state_key)
-                    .unwrap()
-                    .update_regret_sum(a, curr_regret + (cf_action_value - cf_value));
-            }
+    fn params(&self) -> String {
+        self.config.outcome_sampling_params()
+    }
+}
 
-            // Now we need to update the cumulative (average) strategy for each valid action
-            for &a in valid_actions.iter() {
-                let curr_sum = self.strategies.get(&state_key).unwrap().get_strategy_sum(a);
-                let amount = reach_player * udpdated_policy[a] / reach_chance;
-                self.strategies
-                    .get_mut(&state_key)
-                    .unwrap()
-                    .update_strategy_sum(a, amount + curr_sum);
-            }
-        }
-        value_estimate
+impl<S: GameState, A> LoadStrategies<S::Key, A> for OutcomeSamplingSolver<S, A>
+where
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
+{
+    fn load_strategies(&mut self, strategies: HashMap<S::Key, StateNode<A>>) {
+        self.strategies = StrategyStore::from_nodes(strategies, self.config.max_memory_bytes);
     }
+}
 
-    /// Samples a policy depending on on the current player and state
-    fn sample_policy(
-        &self,
-        curr_state: &S,
-        player: usize,
-        strategy: ArrayView1<A>,
-        valid_actions: &[usize],
-    ) -> Array1<A> {
-        let mut action_probs = Array1::zeros(self.num_actions);
-        debug_assert!(valid_actions.len() > 0, "Must have at least 1 valid action");
-        let eps = A::from(EPSILON).unwrap();
-        let num_acts = A::from(valid_actions.len()).unwrap();
-        if curr_state.active_player() == player {
-            for &i in valid_actions.iter() {
-                let prob = (eps / num_acts) + (A::one() - eps) * strategy[i];
-                action_probs[i] = prob;
-            }
-        } else {
-            for &i in valid_actions.iter() {
-                let prob = strategy[i];
-                action_probs[i] = prob;
-            }
-        }
-        action_probs
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::cfr::config::SolverConfig;
+    use crate::cfr::solvers::tests::assert_converges_on_kuhn;
+    use crate::cfr::solvers::Solver;
+    use crate::cfr::state::Game;
+    use crate::kuhn::{KuhnPoker, KuhnState};
+
+    use super::{Baseline, OutcomeSamplingSolver};
+
+    #[test]
+    fn test_converges_on_kuhn() {
+        assert_converges_on_kuhn(OutcomeSamplingSolver::new(2), 100000, 5e-2);
     }
 
-    fn baseline_corrected_value(
-        &self,
-        action_idx: usize,
-        sampled_idx: usize,
-        value: A,
-        sample_prob: A,
-    ) -> A {
-        // Just default this to vanilla CFR as done in Deepmind's impl
-        let baseline = A::zero();
-        if action_idx == sampled_idx {
-            return baseline + (value - baseline) / sample_prob;
-        } else {
-            return baseline;
+    #[test]
+    fn test_converges_on_kuhn_with_baseline() {
+        let mut solver = OutcomeSamplingSolver::new(2);
+        solver.set_baseline(Baseline::Learned(0.5));
+        assert_converges_on_kuhn(solver, 100000, 5e-2);
+    }
+
+    #[test]
+    fn test_memory_limit_evicts_states() {
+        let mut solver = OutcomeSamplingSolver::<KuhnState, f64>::new(2);
+        let mut rng = StdRng::seed_from_u64(0);
+        let initial_state = KuhnPoker::new().start();
+        for i in 0..1000 {
+            solver.update_player_strategy(&initial_state, i % 2, i + 1, &mut rng);
+        }
+        let unbounded = solver.store_stats().unwrap();
+        assert_eq!(unbounded.evicted_states, 0);
+
+        // Only leave room for about half of the states
+        let config = SolverConfig {
+            max_memory_bytes: Some(unbounded.memory_bytes / 2),
+            ..Default::default()
+        };
+        let mut solver =
+            OutcomeSamplingSolver::<KuhnState, f64>::with_config(2, config).unwrap();
+        for i in 0..1000 {
+            solver.update_player_strategy(&initial_state, i % 2, i + 1, &mut rng);
         }
+        let bounded = solver.store_stats().unwrap();
+        assert!(bounded.evicted_states > 0);
+        assert!(bounded.memory_bytes <= unbounded.memory_bytes / 2);
+        assert!(solver.seen_states() < 12);
     }
 }
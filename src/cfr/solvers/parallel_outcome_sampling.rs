@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use ndarray::NdFloat;
+use ndarray_rand::rand_distr::num_traits::Zero;
+use ndarray_rand::rand_distr::uniform::SampleUniform;
+use rand::rngs::StdRng;
+
+use crate::cfr::config::SolverConfig;
+use crate::cfr::metrics;
+use crate::cfr::node::StateNode;
+use crate::cfr::state::GameState;
+use crate::cfr::store::ShardedMap;
+use crate::error::{Error, Result};
+
+use super::outcome_sampling::{outcome_sampling_cfr, SamplingContext};
+use super::{LoadStrategies, ParallelSolver, SolverInfo};
+
+/// Number of independently locked shards the strategies are split into
+const NUM_SHARDS: usize = 256;
+
+/// Outcome sampling variant of CFR where iterations can run concurrently from several threads.
+/// The strategies are kept in a sharded store so threads only contend when they update states
+/// in the same shard. Each node is only locked while it is being read or updated, so concurrent
+/// iterations may observe each other's partial updates, which MCCFR tolerates in practice
+pub struct ParallelOutcomeSamplingSolver<S: GameState, A> {
+    /// Strategies in each game state
+    strategies: ShardedMap<S::Key, StateNode<A>>,
+    /// Number of valid actions in the entire game
+    num_actions: usize,
+    /// Hyperparameters of the solver
+    config: SolverConfig,
+    _a: PhantomData<A>,
+}
+
+impl<S: GameState, A> ParallelOutcomeSamplingSolver<S, A>
+where
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
+{
+    pub fn new(num_actions: usize) -> Self {
+        Self::with_config(num_actions, SolverConfig::default()).expect("Default config is valid")
+    }
+
+    /// Creates a solver with the provided hyperparameters. Fails if any of them is invalid, or if
+    /// a memory limit is set since the sharded store cannot evict states
+    pub fn with_config(num_actions: usize, config: SolverConfig) -> Result<Self> {
+        config.validate()?;
+        if config.max_memory_bytes.is_some() {
+            return Err(Error::Config(
+                "max_memory_bytes is not supported by parallel outcome sampling".to_string(),
+            ));
+        }
+        Ok(Self {
+            strategies: ShardedMap::new(NUM_SHARDS),
+            num_actions,
+            config,
+            _a: PhantomData,
+        })
+    }
+}
+
+impl<S: GameState, A> ParallelSolver<S, A> for ParallelOutcomeSamplingSolver<S, A>
+where
+    S::Key: Send,
+    A: NdFloat
+        + Zero
+        + SampleUniform
+        + Default
+        + PartialOrd
+        + for<'b> std::ops::AddAssign<&'b A>
+        + Send
+        + Sync,
+{
+    fn update_player_strategy(
+        &self,
+        initial_state: &S,
+        player: usize,
+        iteration: usize,
+        rng: &mut StdRng,
+    ) -> A {
+        // Same traversal as the sequential solver, just against the sharded store
+        let mut ctx = SamplingContext::new(&self.config, self.num_actions, player, iteration, rng);
+        outcome_sampling_cfr(
+            &mut &self.strategies,
+            &mut ctx,
+            initial_state,
+            A::one(),
+            A::one(),
+            A::one(),
+        )
+    }
+
+    fn seen_states(&self) -> usize {
+        self.strategies.len()
+    }
+
+    fn strategies_snapshot(&self) -> HashMap<S::Key, StateNode<A>> {
+        self.strategies.to_hash_map()
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.strategies.sum_by(metrics::estimate_memory_bytes::<S::Key, A>)
+    }
+}
+
+impl<S: GameState, A> SolverInfo for ParallelOutcomeSamplingSolver<S, A> {
+    fn name(&self) -> String {
+        "parallel_outcome_sampling".to_string()
+    }
+
+    fn params(&self) -> String {
+        self.config.outcome_sampling_params()
+    }
+}
+
+impl<S: GameState, A> LoadStrategies<S::Key, A> for ParallelOutcomeSamplingSolver<S, A>
+where
+    A: NdFloat
+        + Zero
+        + SampleUniform
+        + Default
+        + PartialOrd
+        + for<'b> std::ops::AddAssign<&'b A>
+        + Send
+        + Sync,
+{
+    fn load_strategies(&mut self, strategies: HashMap<S::Key, StateNode<A>>) {
+        self.strategies = ShardedMap::new(NUM_SHARDS);
+        for (key, node) in strategies {
+            self.strategies.insert(key, node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfr::config::{SolverConfig, TrainerConfig};
+    use crate::cfr::solvers::tests::assert_kuhn_equilibrium;
+    use crate::cfr::state::Game;
+    use crate::cfr::CFRTrainer;
+    use crate::error::Error;
+    use crate::kuhn::{KuhnPoker, KuhnState};
+
+    use super::ParallelOutcomeSamplingSolver;
+
+    #[test]
+    fn test_rejects_memory_limit() {
+        let config = SolverConfig {
+            max_memory_bytes: Some(1 << 20),
+            ..Default::default()
+        };
+        let solver = ParallelOutcomeSamplingSolver::<KuhnState, f64>::with_config(2, config);
+        assert!(matches!(solver, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_converges_on_kuhn() {
+        let game = KuhnPoker::new();
+        let solver = ParallelOutcomeSamplingSolver::<KuhnState, f64>::new(game.num_actions());
+        let config = TrainerConfig {
+            print_steps: 10000,
+            num_threads: Some(4),
+            ..Default::default()
+        };
+        let mut trainer = CFRTrainer::with_config(game, solver, config).unwrap();
+        trainer.train_parallel(100000).unwrap();
+        assert_kuhn_equilibrium(&trainer.get_strategies_snapshot(), 5e-2);
+    }
+}
@@ -1,19 +1,42 @@
 use std::hash::Hash;
 
-pub trait GameState: Sized {
+pub trait GameState: Sized + Clone {
     /// Key associated with the game state
-    type Key: Hash + Eq;
-    /// Gets the active player for the given game state
+    type Key: Hash + Eq + Clone;
+    /// Gets the active player for the given game state. Not meaningful at chance nodes
     fn active_player(&self) -> usize;
     /// Fetches all the available actions in the current game state
     fn valid_actions(&self) -> Vec<usize>;
-    /// Gets the key associated with the game state
+    /// Gets the key associated with the full game state
     fn state_key(&self) -> Self::Key;
+    /// Gets the key of the information set the provided player is in, which only contains what
+    /// that player can observe. Strategies are keyed by this so they never condition on hidden
+    /// information. For games of perfect information this is just the state key
+    fn information_set_key(&self, player: usize) -> Self::Key {
+        let _ = player;
+        self.state_key()
+    }
     /// Returns the next state of the game by applying the provided action for the currently active player
     /// If we are in the terminal state then this returns None
     fn next_state(&self, action: usize) -> Option<Self>;
     /// Checks if the current state is terminal
     fn is_terminal(&self) -> bool;
+    /// Checks if the current state is a chance node, meaning the next state is decided by the
+    /// environment (i.e. dealing cards) rather than by the active player
+    fn is_chance(&self) -> bool {
+        false
+    }
+    /// Gets every outcome of the chance node along with the probability of it occurring.
+    /// The probabilities are expected to sum to 1
+    fn chance_outcomes(&self) -> Vec<(usize, f32)> {
+        Vec::new()
+    }
+    /// Returns the next state of the game by applying the provided outcome at a chance node.
+    /// Games with chance nodes must implement this, it is never called otherwise
+    fn apply_chance_outcome(&self, outcome: usize) -> Self {
+        let _ = outcome;
+        unreachable!("The game does not have chance nodes")
+    }
     /// Gets the payout for the player at this state
     fn get_reward(&self, player: usize) -> f32;
 }
@@ -28,6 +51,21 @@ pub trait Game {
     fn num_actions(&self) -> usize;
     /// Starts the game and retrieves the initial state
     fn start(&self) -> Self::State;
+    /// Starts the game with any randomness that is not modelled by chance nodes decided by the
+    /// provided seed, so the same seed always replays the same game. Games whose randomness is
+    /// entirely made of chance nodes ignore the seed, as the solvers sample those outcomes with
+    /// their own seeded random number generator
+    fn start_with_seed(&self, seed: u64) -> Self::State {
+        let _ = seed;
+        self.start()
+    }
     /// Resets the game to an initial state and clears all scores/actions of each player
     fn reset(&mut self);
+    /// Name of the game. Checkpoints record this so they are not loaded into a different game
+    fn name(&self) -> String;
+    /// Parameters the game was created with (i.e. the board size), formatted for display.
+    /// Strategies trained with different parameters are not interchangeable
+    fn params(&self) -> String {
+        String::new()
+    }
 }
@@ -0,0 +1,375 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::mem::size_of;
+use std::sync::Mutex;
+
+use super::metrics;
+use super::node::StateNode;
+
+/// Eviction frees memory until the store uses at most `1 - 1 / EVICTION_HEADROOM` of its limit.
+/// Evicting below the limit means the (relatively expensive) eviction pass does not run again on
+/// the very next insert
+const EVICTION_HEADROOM: usize = 10;
+
+/// Hash map that is split into shards which are each guarded by their own lock, so several
+/// threads can update entries concurrently as long as they land in different shards
+pub struct ShardedMap<K, V> {
+    shards: Vec<Mutex<HashMap<K, V>>>,
+    /// Hasher used to pick the shard of a key
+    hasher: RandomState,
+}
+
+impl<K, V> ShardedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new(num_shards: usize) -> Self {
+        assert!(num_shards > 0, "Must have at least 1 shard");
+        Self {
+            shards: (0..num_shards)
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, key: &K) -> &Mutex<HashMap<K, V>> {
+        &self.shards[self.hasher.hash_one(key) as usize % self.shards.len()]
+    }
+
+    /// Runs the provided function on the value of the key while holding the lock of its shard.
+    /// If the key is not in the map, it is inserted with the default value first
+    pub fn update<R>(
+        &self,
+        key: &K,
+        default: impl FnOnce() -> V,
+        f: impl FnOnce(&mut V) -> R,
+    ) -> R {
+        let mut shard = self.shard(key).lock().unwrap();
+        if !shard.contains_key(key) {
+            shard.insert(key.clone(), default());
+        }
+        f(shard.get_mut(key).unwrap())
+    }
+
+    /// Runs the provided function on the value of the key while holding the lock of its shard,
+    /// if the key is in the map
+    pub fn update_existing<R>(&self, key: &K, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        self.shard(key).lock().unwrap().get_mut(key).map(f)
+    }
+
+    /// Inserts the value for the key, replacing any existing value
+    pub fn insert(&self, key: K, value: V) {
+        self.shard(&key).lock().unwrap().insert(key, value);
+    }
+
+    /// Number of entries across every shard
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    /// Sums the provided function over every value. Shards are locked one at a time
+    pub fn sum_by(&self, f: impl Fn(&V) -> usize) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.lock().unwrap().values().map(&f).sum::<usize>())
+            .sum()
+    }
+
+    /// Copies every entry into a regular hash map. Shards are locked one at a time, so entries
+    /// may be updated by other threads while the copy is taken
+    pub fn to_hash_map(&self) -> HashMap<K, V>
+    where
+        V: Clone,
+    {
+        let mut map = HashMap::new();
+        for shard in self.shards.iter() {
+            let shard = shard.lock().unwrap();
+            map.extend(shard.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        map
+    }
+}
+
+/// Access to the strategy of each state, so a single traversal can run against both the
+/// sequential and the sharded stores
+pub trait NodeStore<K, A> {
+    /// Records a visit to the state and runs the provided function on its strategy. If the
+    /// state has no strategy yet, it is inserted with the default value first
+    fn visit_node<R>(
+        &mut self,
+        key: &K,
+        default: impl FnOnce() -> StateNode<A>,
+        f: impl FnOnce(&mut StateNode<A>) -> R,
+    ) -> R;
+    /// Runs the provided function on the strategy of a state that was already visited during
+    /// the current iteration
+    fn update_node<R>(&mut self, key: &K, f: impl FnOnce(&mut StateNode<A>) -> R) -> R;
+}
+
+impl<K, A> NodeStore<K, A> for &ShardedMap<K, StateNode<A>>
+where
+    K: Hash + Eq + Clone,
+{
+    fn visit_node<R>(
+        &mut self,
+        key: &K,
+        default: impl FnOnce() -> StateNode<A>,
+        f: impl FnOnce(&mut StateNode<A>) -> R,
+    ) -> R {
+        self.update(key, default, f)
+    }
+
+    fn update_node<R>(&mut self, key: &K, f: impl FnOnce(&mut StateNode<A>) -> R) -> R {
+        self.update_existing(key, f)
+            .expect("Sharded maps never remove states")
+    }
+}
+
+/// Statistics about the memory used by a strategy store and the states it has evicted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreStats {
+    /// Estimate of the memory used by the stored strategies, in bytes
+    pub memory_bytes: usize,
+    /// Memory the store is allowed to use before it evicts states, if it is bounded
+    pub memory_limit: Option<usize>,
+    /// Number of states evicted since the store was created
+    pub evicted_states: usize,
+    /// Number of times the store went over its limit and had to evict states
+    pub eviction_passes: usize,
+}
+
+/// Strategies of every visited state along with how often each state was visited. If the store
+/// has a memory limit, the least visited states are evicted whenever the limit is exceeded.
+/// An evicted state loses everything it has learned and starts from scratch if it is visited
+/// again, which is a good trade off for games like scrabble where most states are only ever
+/// visited once
+pub struct StrategyStore<K, A> {
+    nodes: HashMap<K, StateNode<A>>,
+    /// Number of times each state was visited. Counts are halved after every eviction pass so
+    /// states that were only popular early in training can eventually be evicted
+    visits: HashMap<K, u32>,
+    /// Maximum number of bytes the store may use, if any
+    memory_limit: Option<usize>,
+    /// Estimate of the memory used by the store. Nodes are measured when they are inserted and
+    /// remeasured during eviction, so this only undercounts the entries added in between
+    memory_bytes: usize,
+    evicted_states: usize,
+    eviction_passes: usize,
+}
+
+impl<K, A> StrategyStore<K, A>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Creates an empty store. Nothing is ever evicted if there is no memory limit
+    pub fn new(memory_limit: Option<usize>) -> Self {
+        Self::from_nodes(HashMap::new(), memory_limit)
+    }
+
+    /// Creates a store that starts with the provided strategies, i.e. ones restored from a
+    /// checkpoint. None of the states have been visited yet
+    pub fn from_nodes(nodes: HashMap<K, StateNode<A>>, memory_limit: Option<usize>) -> Self {
+        let visits = nodes.keys().map(|k| (k.clone(), 0)).collect();
+        let memory_bytes = nodes.values().map(Self::entry_bytes).sum();
+        Self {
+            nodes,
+            visits,
+            memory_limit,
+            memory_bytes,
+            evicted_states: 0,
+            eviction_passes: 0,
+        }
+    }
+
+    /// Estimate of the memory used by a single state, including its visit count
+    fn entry_bytes(node: &StateNode<A>) -> usize {
+        metrics::estimate_memory_bytes::<K, A>(node) + size_of::<u32>()
+    }
+
+    /// Records a visit to the state and gets its strategy. If the state has no strategy yet, it
+    /// is inserted with the default value first
+    pub fn visit(&mut self, key: &K, default: impl FnOnce() -> StateNode<A>) -> &mut StateNode<A> {
+        if !self.nodes.contains_key(key) {
+            let node = default();
+            self.memory_bytes += Self::entry_bytes(&node);
+            self.nodes.insert(key.clone(), node);
+        }
+        let visits = self.visits.entry(key.clone()).or_insert(0);
+        *visits = visits.saturating_add(1);
+        self.nodes.get_mut(key).unwrap()
+    }
+
+    /// Updates the strategy of a state that is already in the store, keeping track of how much
+    /// the node grew (e.g. from a learned baseline being added to it)
+    pub fn update<R>(&mut self, key: &K, f: impl FnOnce(&mut StateNode<A>) -> R) -> Option<R> {
+        let node = self.nodes.get_mut(key)?;
+        let bytes_before = node.memory_bytes();
+        let result = f(node);
+        self.memory_bytes = self.memory_bytes + node.memory_bytes() - bytes_before;
+        Some(result)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&StateNode<A>> {
+        self.nodes.get(key)
+    }
+
+    /// Strategies of every state in the store
+    pub fn nodes(&self) -> &HashMap<K, StateNode<A>> {
+        &self.nodes
+    }
+
+    /// Number of states in the store
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of times the state was visited since the last eviction pass
+    pub fn visits(&self, key: &K) -> u32 {
+        self.visits.get(key).copied().unwrap_or(0)
+    }
+
+    pub fn stats(&self) -> StoreStats {
+        StoreStats {
+            memory_bytes: self.memory_bytes,
+            memory_limit: self.memory_limit,
+            evicted_states: self.evicted_states,
+            eviction_passes: self.eviction_passes,
+        }
+    }
+
+    /// Evicts the least visited states if the store is over its memory limit. This must not be
+    /// called while a state is being updated, since that state may be evicted. Returns the keys
+    /// of the evicted states
+    pub fn enforce_limit(&mut self) -> Vec<K> {
+        let limit = match self.memory_limit {
+            Some(limit) if self.memory_bytes > limit => limit,
+            _ => return Vec::new(),
+        };
+        // Nodes may have grown since they were inserted
+        self.memory_bytes = self.nodes.values().map(Self::entry_bytes).sum();
+        if self.memory_bytes <= limit {
+            return Vec::new();
+        }
+
+        let target = limit - limit / EVICTION_HEADROOM;
+        let mut candidates = self
+            .visits
+            .iter()
+            .map(|(k, &v)| (v, k.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(v, _)| *v);
+        let mut evicted = Vec::new();
+        for (_, key) in candidates {
+            if self.memory_bytes <= target {
+                break;
+            }
+            let node = self.nodes.remove(&key).unwrap();
+            self.visits.remove(&key);
+            self.memory_bytes -= Self::entry_bytes(&node);
+            evicted.push(key);
+        }
+        for v in self.visits.values_mut() {
+            *v /= 2;
+        }
+        self.evicted_states += evicted.len();
+        self.eviction_passes += 1;
+        evicted
+    }
+}
+
+impl<K, A> NodeStore<K, A> for StrategyStore<K, A>
+where
+    K: Hash + Eq + Clone,
+{
+    fn visit_node<R>(
+        &mut self,
+        key: &K,
+        default: impl FnOnce() -> StateNode<A>,
+        f: impl FnOnce(&mut StateNode<A>) -> R,
+    ) -> R {
+        f(self.visit(key, default))
+    }
+
+    fn update_node<R>(&mut self, key: &K, f: impl FnOnce(&mut StateNode<A>) -> R) -> R {
+        self.update(key, f)
+            .expect("States are only evicted between iterations")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::cfr::node::StateNode;
+
+    use super::{ShardedMap, StrategyStore};
+
+    #[test]
+    fn test_concurrent_updates() {
+        let map = ShardedMap::<usize, usize>::new(4);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for key in 0..100 {
+                        map.update(&key, || 0, |v| *v += 1);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), 100);
+        let map = map.to_hash_map();
+        assert!(map.values().all(|&v| v == 8));
+    }
+
+    #[test]
+    fn test_evicts_least_visited_states() {
+        let node_bytes =
+            StrategyStore::<usize, f32>::entry_bytes(&StateNode::with_actions(10, &[0, 1]));
+        let mut store = StrategyStore::<usize, f32>::new(Some(node_bytes * 10));
+        for key in 0..10 {
+            for _ in 0..=key {
+                store.visit(&key, || StateNode::with_actions(10, &[0, 1]));
+            }
+        }
+        assert!(store.enforce_limit().is_empty());
+
+        for _ in 0..3 {
+            store.visit(&10, || StateNode::with_actions(10, &[0, 1]));
+        }
+        let mut evicted = store.enforce_limit();
+        evicted.sort();
+        assert_eq!(evicted, vec![0, 1]);
+        assert_eq!(store.len(), 9);
+        assert!(store.get(&10).is_some());
+        assert_eq!(store.visits(&9), 5);
+
+        let stats = store.stats();
+        assert_eq!(stats.evicted_states, 2);
+        assert_eq!(stats.eviction_passes, 1);
+        assert!(stats.memory_bytes <= node_bytes * 9);
+    }
+
+    #[test]
+    fn test_counts_node_growth() {
+        let node_bytes =
+            StrategyStore::<usize, f32>::entry_bytes(&StateNode::with_actions(10, &[0, 1]));
+        let mut store = StrategyStore::<usize, f32>::new(Some(node_bytes * 2));
+        for key in 0..2 {
+            for _ in 0..=key {
+                store.visit(&key, || StateNode::with_actions(10, &[0, 1]));
+            }
+        }
+        assert!(store.enforce_limit().is_empty());
+
+        // Learning a baseline grows the node past the limit, so the least visited state goes
+        store.update(&1, |node| node.update_baseline(0, 1, 1.0, 0.5));
+        let grown_bytes = StrategyStore::<usize, f32>::entry_bytes(store.get(&1).unwrap());
+        assert!(grown_bytes > node_bytes);
+        assert_eq!(store.stats().memory_bytes, node_bytes + grown_bytes);
+        assert_eq!(store.enforce_limit(), vec![0]);
+        assert_eq!(store.stats().memory_bytes, grown_bytes);
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use fst::{Map, MapBuilder};
+use memmap2::Mmap;
+use ndarray::NdFloat;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::utils::temp_file::TempFile;
+
+use super::checkpoint::{Checkpoint, TypeTag};
+use super::node::StateNode;
+
+/// Bytes every strategy file starts with
+const MAGIC: &[u8; 8] = b"CFRSTRAT";
+/// Version of the strategy file format. Bump this whenever the layout changes
+const VERSION: u32 = 1;
+/// Size of the header: the magic bytes, version, number of actions and length of the data
+const HEADER_LEN: usize = 8 + 4 + 8 + 8;
+/// Size of a single stored action: its id and probability
+const ENTRY_LEN: usize = 4 + 4;
+
+/// Keys that can be stored in a strategy file. The bytes of two different keys must never be
+/// the same
+pub trait ByteKey {
+    fn key_bytes(&self) -> Vec<u8>;
+}
+
+impl ByteKey for String {
+    fn key_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl ByteKey for u64 {
+    fn key_bytes(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+}
+
+impl ByteKey for u128 {
+    fn key_bytes(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+}
+
+/// Range of a memory-mapped file, so the index can be read straight from the mapping
+struct MmapSlice {
+    mmap: Arc<Mmap>,
+    start: usize,
+}
+
+impl AsRef<[u8]> for MmapSlice {
+    fn as_ref(&self) -> &[u8] {
+        &self.mmap[self.start..]
+    }
+}
+
+/// Average strategies stored in a memory-mapped file, so they can be played without decoding
+/// the whole table up front. Only the pages of the states that are looked up are ever read
+/// from disk, which lets agents start instantly even with huge tables.
+///
+/// The file starts with a header, followed by the non-zero probabilities of each state, followed
+/// by an `fst::Map` from the bytes of each key to the offset of its probabilities
+pub struct MappedStrategies {
+    mmap: Arc<Mmap>,
+    index: Map<MmapSlice>,
+    /// Number of actions in the entire game
+    num_actions: usize,
+}
+
+impl MappedStrategies {
+    /// Writes the average strategies of every state to a strategy file. Like checkpoints, the
+    /// file is written to a temporary file that is renamed once it is complete
+    pub fn write<K, A, P>(path: P, strategies: &HashMap<K, StateNode<A>>) -> Result<()>
+    where
+        K: ByteKey,
+        A: NdFloat,
+        P: AsRef<Path>,
+    {
+        let tmp_file = TempFile::for_path(&path);
+        Self::write_file(tmp_file.path(), strategies)?;
+        tmp_file.persist(path)?;
+        Ok(())
+    }
+
+    /// Writes the average strategies of a checkpoint to a strategy file, so strategies that
+    /// were trained before strategy files existed (or whose run was interrupted) can be played
+    /// without decoding the checkpoint. Returns the number of states that were written
+    pub fn convert_checkpoint<K, A, P, Q>(checkpoint_path: P, path: Q) -> Result<usize>
+    where
+        K: ByteKey + Hash + Eq + Serialize + DeserializeOwned + TypeTag,
+        A: NdFloat + Serialize + DeserializeOwned + TypeTag,
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let checkpoint = Checkpoint::<K, A>::load(checkpoint_path)?;
+        Self::write(path, &checkpoint.strategies)?;
+        Ok(checkpoint.strategies.len())
+    }
+
+    fn write_file<K, A, P>(path: P, strategies: &HashMap<K, StateNode<A>>) -> Result<()>
+    where
+        K: ByteKey,
+        A: NdFloat,
+        P: AsRef<Path>,
+    {
+        // The index must be built in lexicographic order of the keys
+        let mut nodes = strategies
+            .iter()
+            .map(|(k, node)| (k.key_bytes(), node))
+            .collect::<Vec<_>>();
+        nodes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let num_actions = nodes.first().map_or(0, |(_, node)| node.num_actions());
+
+        let mut data = Vec::new();
+        let mut index = MapBuilder::memory();
+        for (key, node) in nodes.iter() {
+            index.insert(key, data.len() as u64)?;
+            let avg_strategy = node.get_average_strategy();
+            let entries = node
+                .actions()
+                .iter()
+                .zip(avg_strategy.iter())
+                .filter(|(_, &p)| p > A::zero())
+                .collect::<Vec<_>>();
+            data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+            for (&a, p) in entries {
+                data.extend_from_slice(&(a as u32).to_le_bytes());
+                data.extend_from_slice(&p.to_f32().unwrap().to_le_bytes());
+            }
+        }
+        let index = index.into_inner()?;
+
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(&file);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(num_actions as u64).to_le_bytes())?;
+        writer.write_all(&(data.len() as u64).to_le_bytes())?;
+        writer.write_all(&data)?;
+        writer.write_all(&index)?;
+        writer.flush()?;
+        drop(writer);
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Maps a strategy file into memory. Only the header is checked, so this returns as soon as
+    /// the file is opened regardless of its size
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        // The file must not be modified while it is mapped. Strategy files are only ever
+        // replaced by renaming a new file over them, which leaves the mapped file untouched
+        let mmap = Arc::new(unsafe { Mmap::map(&file)? });
+        if mmap.len() < HEADER_LEN || &mmap[..8] != MAGIC {
+            return Err(Error::StrategyFile(
+                "file is not a strategy file".to_string(),
+            ));
+        }
+        let version = u32::from_le_bytes(mmap[8..12].try_into().unwrap());
+        if version != VERSION {
+            return Err(Error::StrategyFile(format!(
+                "file has format version {} but version {} is expected",
+                version, VERSION
+            )));
+        }
+        let num_actions = u64::from_le_bytes(mmap[12..20].try_into().unwrap()) as usize;
+        let data_len = u64::from_le_bytes(mmap[20..28].try_into().unwrap()) as usize;
+        if HEADER_LEN + data_len > mmap.len() {
+            return Err(Error::StrategyFile("file is truncated".to_string()));
+        }
+        let index = Map::new(MmapSlice {
+            mmap: mmap.clone(),
+            start: HEADER_LEN + data_len,
+        })?;
+        Ok(Self {
+            mmap,
+            index,
+            num_actions,
+        })
+    }
+
+    /// Number of states that have a strategy
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Number of actions in the entire game
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
+    }
+
+    /// Gets the average strategy to be played in the state, if it has one. Only the actions
+    /// with a non-zero probability are returned, along with their probability
+    pub fn average_strategy<K: ByteKey>(&self, key: &K) -> Option<Vec<(usize, f32)>> {
+        let offset = HEADER_LEN + self.index.get(key.key_bytes())? as usize;
+        let read_u32 = |pos: usize| {
+            let bytes = self.mmap.get(pos..pos + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        let len = read_u32(offset)? as usize;
+        (0..len)
+            .map(|i| {
+                let pos = offset + 4 + i * ENTRY_LEN;
+                let action = read_u32(pos)? as usize;
+                let prob = f32::from_bits(read_u32(pos + 4)?);
+                Some((action, prob))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cfr::checkpoint::{Checkpoint, CheckpointMetadata};
+    use crate::cfr::node::StateNode;
+    use crate::error::Error;
+    use crate::kuhn::KuhnPoker;
+
+    use super::MappedStrategies;
+
+    #[test]
+    fn test_write_and_open() {
+        let mut strategies = HashMap::new();
+        for key in 0..100u128 {
+            let mut node = StateNode::<f64>::with_actions(1000, &[key as usize, 999]);
+            node.update_strategy_sum(key as usize, 3.0);
+            node.update_strategy_sum(999, 1.0);
+            strategies.insert(key << 64, node);
+        }
+        let path = std::env::temp_dir().join("cfr_strategy_file_test.strat");
+        MappedStrategies::write(&path, &strategies).unwrap();
+        let mapped = MappedStrategies::open(&path).unwrap();
+        let invalid_path = std::env::temp_dir().join("cfr_strategy_file_invalid_test.strat");
+        std::fs::write(&invalid_path, b"not a strategy file").unwrap();
+        let invalid = MappedStrategies::open(&invalid_path);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&invalid_path).unwrap();
+
+        assert_eq!(mapped.len(), 100);
+        assert_eq!(mapped.num_actions(), 1000);
+        for (key, node) in strategies.iter() {
+            let expected = node
+                .actions()
+                .iter()
+                .copied()
+                .zip(node.get_average_strategy().into_iter().map(|p| p as f32))
+                .collect::<Vec<_>>();
+            assert_eq!(mapped.average_strategy(key), Some(expected));
+        }
+        assert_eq!(mapped.average_strategy(&1u128), None);
+        assert!(matches!(invalid, Err(Error::StrategyFile(_))));
+    }
+
+    #[test]
+    fn test_convert_checkpoint() {
+        let mut node = StateNode::<f32>::new(2);
+        node.update_strategy_sum(0, 1.0);
+        node.update_strategy_sum(1, 3.0);
+        let mut strategies = HashMap::new();
+        strategies.insert("root".to_string(), node);
+        let metadata =
+            CheckpointMetadata::new::<_, f32>(&KuhnPoker::new(), "cfr_plus".into(), "".into());
+        let ckpt_path = std::env::temp_dir().join("cfr_strategy_file_convert_test.ckpt");
+        let path = std::env::temp_dir().join("cfr_strategy_file_convert_test.strat");
+        Checkpoint::save(&ckpt_path, &metadata, &[0.0, 0.0], &strategies).unwrap();
+
+        let converted =
+            MappedStrategies::convert_checkpoint::<String, f32, _, _>(&ckpt_path, &path);
+        let mapped = MappedStrategies::open(&path).unwrap();
+        std::fs::remove_file(&ckpt_path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(converted.unwrap(), 1);
+        assert_eq!(
+            mapped.average_strategy(&"root".to_string()),
+            Some(vec![(0, 0.25), (1, 0.75)])
+        );
+    }
+}
@@ -1,83 +1,624 @@
 use std::collections::HashMap;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use ndarray::NdFloat;
 use ndarray_rand::rand_distr::num_traits::Zero;
 use ndarray_rand::rand_distr::uniform::SampleUniform;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
-use crate::cfr::solvers::OutcomeSamplingSolver;
-use crate::utils::{self, serialization};
+use crate::cfr::checkpoint::{self, Checkpoint, CheckpointMetadata, TypeTag};
+use crate::cfr::config::TrainerConfig;
+use crate::cfr::eval;
+use crate::cfr::metrics::{self, MetricsLogger};
+use crate::cfr::solvers::{LoadStrategies, ParallelSolver, Solver, SolverInfo};
+use crate::error::Result;
 
 use super::node::StateNode;
 use super::state::{Game, GameState};
 
-pub struct CFRTrainer<G, A>
+pub struct CFRTrainer<G, A, S>
 where
     G: Game,
     A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
 {
     /// The game to train on
     game: G,
-    /// Strategies for each player in the game
-    strategies: HashMap<<G::State as GameState>::Key, StateNode<A>>,
+    /// Variant of CFR used to update the strategies on each round of training
+    solver: S,
+    /// Logging and checkpoint settings
+    config: TrainerConfig,
+    /// Number of rounds completed so far, including those from a restored checkpoint
+    iteration: usize,
+    /// Cumulative utility of each player over all completed rounds
+    cumulative_utility: Vec<A>,
+    /// Wall-clock time spent training so far, in seconds
+    training_secs: f64,
+    /// Exploitability of the average strategy at the last evaluation
+    exploitability: Option<f64>,
+    /// Lowest exploitability of any strategy saved as the best checkpoint
+    best_exploitability: Option<f64>,
+    /// Log of the training progress, opened when training starts
+    metrics: Option<MetricsLogger>,
+    /// Seed that the random number generator of every round is derived from
+    seed: u64,
+    _a: PhantomData<A>,
 }
 
-impl<G, A> CFRTrainer<G, A>
+impl<G, A, S> CFRTrainer<G, A, S>
 where
     G: Game,
-    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A> + Serialize + DeserializeOwned,
-    <G::State as GameState>::Key: Serialize + DeserializeOwned
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A>,
 {
-    pub fn new(game: G) -> Self {
-        Self {
+    pub fn new(game: G, solver: S) -> Self {
+        Self::with_config(game, solver, TrainerConfig::default()).expect("Default config is valid")
+    }
+
+    /// Creates a trainer that starts from scratch. Fails if the config is invalid
+    pub fn with_config(game: G, solver: S, config: TrainerConfig) -> Result<Self> {
+        config.validate()?;
+        let cumulative_utility = vec![A::zero(); game.num_players()];
+        let seed = config.seed.unwrap_or_else(rand::random);
+        Ok(Self {
+            game,
+            solver,
+            config,
+            iteration: 0,
+            cumulative_utility,
+            training_secs: 0.0,
+            exploitability: None,
+            best_exploitability: None,
+            metrics: None,
+            seed,
+            _a: PhantomData,
+        })
+    }
+
+    /// Creates a trainer that continues the training run saved in the checkpoint. The solver's
+    /// strategies are replaced with the ones in the checkpoint, and the round counter and
+    /// cumulative utilities pick up where they left off. Training continues with the seed of the
+    /// checkpoint unless the config sets a different one. Fails if the checkpoint was trained on
+    /// a different game, by a different solver or with different solver hyperparameters, with a
+    /// different floating point type, or if the config is invalid
+    pub fn from_checkpoint<P: AsRef<Path>>(
+        game: G,
+        mut solver: S,
+        config: TrainerConfig,
+        path: P,
+    ) -> Result<Self>
+    where
+        A: Serialize + DeserializeOwned + TypeTag,
+        <G::State as GameState>::Key: Serialize + DeserializeOwned + TypeTag,
+        S: LoadStrategies<<G::State as GameState>::Key, A> + SolverInfo,
+    {
+        config.validate()?;
+        let checkpoint =
+            Checkpoint::load_for_solver(path, &game, &solver.name(), &solver.params())?;
+        solver.load_strategies(checkpoint.strategies);
+        // Only replace the best checkpoint with strategies that improve on it
+        let best_path = config.best_ckpt_path();
+        let best_exploitability = if config.keep_best_ckpt && best_path.exists() {
+            checkpoint::read_metadata(best_path)?.exploitability
+        } else {
+            None
+        };
+        let seed = config
+            .seed
+            .or(checkpoint.metadata.seed)
+            .unwrap_or_else(rand::random);
+        Ok(Self {
             game,
-            strategies: HashMap::new(),
+            solver,
+            config,
+            iteration: checkpoint.metadata.iteration,
+            cumulative_utility: checkpoint.cumulative_utility,
+            training_secs: checkpoint.metadata.training_secs,
+            exploitability: checkpoint.metadata.exploitability,
+            best_exploitability,
+            metrics: None,
+            seed,
+            _a: PhantomData,
+        })
+    }
+
+    /// Number of rounds completed so far
+    pub fn iteration(&self) -> usize {
+        self.iteration
+    }
+
+    /// Seed that the randomness of every round is derived from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Describes the current state of training for a checkpoint
+    fn checkpoint_metadata(&self, solver: String, solver_params: String) -> CheckpointMetadata
+    where
+        A: TypeTag,
+        <G::State as GameState>::Key: TypeTag,
+    {
+        let mut metadata = CheckpointMetadata::new::<G, A>(&self.game, solver, solver_params);
+        metadata.iteration = self.iteration;
+        metadata.training_secs = self.training_secs;
+        metadata.seed = Some(self.seed);
+        metadata.exploitability = self.exploitability;
+        metadata
+    }
+
+    /// Saves the current state of training to the provided path
+    fn save_checkpoint(
+        &self,
+        path: PathBuf,
+        solver: String,
+        solver_params: String,
+        strategies: &HashMap<<G::State as GameState>::Key, StateNode<A>>,
+    ) -> Result<()>
+    where
+        A: Serialize + DeserializeOwned + TypeTag,
+        <G::State as GameState>::Key: Serialize + DeserializeOwned + TypeTag,
+    {
+        let metadata = self.checkpoint_metadata(solver, solver_params);
+        fs::create_dir_all(&self.config.ckpt_dir)?;
+        Checkpoint::save(path, &metadata, &self.cumulative_utility, strategies)?;
+        Ok(())
+    }
+
+    /// Deletes all but the most recent `keep_ckpts` checkpoints
+    fn remove_old_checkpoints(&self) -> Result<()> {
+        if let Some(keep) = self.config.keep_ckpts {
+            let ckpts = self.config.list_ckpts()?;
+            let num_old = ckpts.len().saturating_sub(keep);
+            for (_, path) in ckpts.into_iter().take(num_old) {
+                fs::remove_file(path)?;
+            }
         }
+        Ok(())
     }
 
+    /// Opens the metrics log if one is configured and it is not open yet
+    fn open_metrics(&mut self) -> Result<()> {
+        if let (Some(path), None) = (self.config.metrics_path.clone(), &self.metrics) {
+            self.metrics = Some(MetricsLogger::create(
+                path,
+                self.config.metrics_format,
+                self.iteration,
+                self.utility_f64(),
+            )?);
+        }
+        Ok(())
+    }
+
+    /// Appends the current progress to the metrics log, if there is one
+    fn log_metrics(
+        &mut self,
+        visited_states: usize,
+        memory_bytes: usize,
+        evicted_states: usize,
+        exploitability: Option<f64>,
+    ) -> Result<()> {
+        let cumulative_utility = self.utility_f64();
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.log(
+                self.iteration,
+                cumulative_utility,
+                visited_states,
+                memory_bytes,
+                evicted_states,
+                exploitability,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn utility_f64(&self) -> Vec<f64> {
+        self.cumulative_utility.iter().map(|u| u.to_f64().unwrap()).collect()
+    }
+
+    /// Records the exploitability of the latest evaluation. Returns true if the strategy should
+    /// be saved as the new best checkpoint
+    fn record_exploitability(&mut self, exploitability: f64) -> bool {
+        self.exploitability = Some(exploitability);
+        let improved = self.best_exploitability.map_or(true, |best| exploitability < best);
+        if self.config.keep_best_ckpt && improved {
+            self.best_exploitability = Some(exploitability);
+            return true;
+        }
+        false
+    }
+
+    /// Logs the exploitability of the average strategy every `eval_steps` rounds. Computing the
+    /// exploitability enumerates the whole game tree, so this should only be used on small games
+    pub fn set_eval_steps(&mut self, eval_steps: usize) {
+        self.config.eval_steps = Some(eval_steps);
+    }
+}
+
+impl<G, A, S> CFRTrainer<G, A, S>
+where
+    G: Game,
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A> + Serialize + DeserializeOwned + TypeTag,
+    <G::State as GameState>::Key: Serialize + DeserializeOwned + TypeTag,
+    S: Solver<G::State, A>,
+{
     pub fn get_strategies(&self) -> &HashMap<<G::State as GameState>::Key, StateNode<A>> {
-        &self.strategies
+        self.solver.strategies()
     }
-    pub fn train(&mut self, rounds: usize, print_steps: usize, ckpt_steps: usize) {
+    /// Runs the provided number of rounds, continuing from the rounds that were already completed
+    pub fn train(&mut self, rounds: usize) -> Result<()> {
         println!("Starting CFR Trainer for {} rounds", rounds);
-        let mut cumulative_utility = Vec::new();
-        cumulative_utility.resize(self.game.num_players(), A::zero());
+        self.open_metrics()?;
+        let mut timer = Instant::now();
+
+        for _ in 0..rounds {
+            self.iteration += 1;
+            let i = self.iteration;
+            let mut rng = round_rng(self.seed, i);
+            let initial_state = self.game.start_with_seed(rng.gen());
+            for p in 0..self.game.num_players() {
+                let util = self.solver.update_player_strategy(&initial_state, p, i, &mut rng);
+                self.cumulative_utility[p] += util;
+            }
+            if i.is_multiple_of(self.config.print_steps) {
+                println!("Round: {}", i);
+                println!("\tUtility (Cumulative): {:?}", self.cumulative_utility);
+                println!("\tVisited States: {}", self.solver.seen_states());
+                if let Some(stats) = self.solver.store_stats() {
+                    println!(
+                        "\tEvicted States: {} ({} passes)",
+                        stats.evicted_states, stats.eviction_passes
+                    );
+                }
+            }
+
+            let mut exploitability = None;
+            if let Some(eval_steps) = self.config.eval_steps {
+                if i.is_multiple_of(eval_steps) {
+                    let report = eval::exploitability(&self.game, self.solver.strategies());
+                    println!("Round: {}", i);
+                    println!("\tExploitability: {}", report.exploitability);
+                    println!("\tNashConv: {}", report.nash_conv);
+                    exploitability = Some(report.exploitability);
+                    if self.record_exploitability(report.exploitability) {
+                        println!("Saving Best Strategy");
+                        self.training_secs += timer.elapsed().as_secs_f64();
+                        timer = Instant::now();
+                        self.save_checkpoint(
+                            self.config.best_ckpt_path(),
+                            self.solver.name(),
+                            self.solver.params(),
+                            self.solver.strategies(),
+                        )?;
+                    }
+                }
+            }
+            let log_due = i.is_multiple_of(self.config.print_steps) || exploitability.is_some();
+            if log_due && self.metrics.is_some() {
+                let memory_bytes = self
+                    .solver
+                    .strategies()
+                    .values()
+                    .map(metrics::estimate_memory_bytes::<<G::State as GameState>::Key, A>)
+                    .sum();
+                let evicted_states = self
+                    .solver
+                    .store_stats()
+                    .map_or(0, |stats| stats.evicted_states);
+                self.log_metrics(
+                    self.solver.seen_states(),
+                    memory_bytes,
+                    evicted_states,
+                    exploitability,
+                )?;
+            }
 
-        let mut policy = OutcomeSamplingSolver::<G::State, A>::new(
-            &mut self.strategies,
-            self.game.num_actions(),
+            if let Some(ckpt_steps) = self.config.ckpt_steps {
+                if i.is_multiple_of(ckpt_steps) {
+                    println!("Saving Current Strategy");
+                    self.training_secs += timer.elapsed().as_secs_f64();
+                    timer = Instant::now();
+                    self.save_checkpoint(
+                        self.config.ckpt_path(i),
+                        self.solver.name(),
+                        self.solver.params(),
+                        self.solver.strategies(),
+                    )?;
+                    self.remove_old_checkpoints()?;
+                }
+            }
+        }
+        self.training_secs += timer.elapsed().as_secs_f64();
+        println!("CFR Training Complete");
+        Ok(())
+    }
+}
+
+impl<G, A, S> CFRTrainer<G, A, S>
+where
+    G: Game + Sync,
+    G::State: Send + Sync,
+    A: NdFloat + Zero + SampleUniform + Default + PartialOrd + for<'b> std::ops::AddAssign<&'b A> + Serialize + DeserializeOwned + TypeTag + Send + Sync,
+    <G::State as GameState>::Key: Serialize + DeserializeOwned + TypeTag,
+    S: ParallelSolver<G::State, A>,
+{
+    /// Gets a snapshot of the strategies learned so far
+    pub fn get_strategies_snapshot(&self) -> HashMap<<G::State as GameState>::Key, StateNode<A>> {
+        self.solver.strategies_snapshot()
+    }
+
+    /// Trains with several iterations running concurrently on a rayon thread pool, continuing
+    /// from the rounds that were already completed. Rounds are run in batches of `print_steps`,
+    /// and progress, evaluation and checkpoints are handled in between batches
+    pub fn train_parallel(&mut self, rounds: usize) -> Result<()> {
+        let mut builder = rayon::ThreadPoolBuilder::new();
+        if let Some(num_threads) = self.config.num_threads {
+            builder = builder.num_threads(num_threads);
+        }
+        let pool = builder.build()?;
+        println!(
+            "Starting parallel CFR Trainer for {} rounds on {} threads",
+            rounds,
+            pool.current_num_threads()
         );
+        self.open_metrics()?;
 
-        for i in 0..rounds {
-            let initial_state = self.game.start();
-            for p in 0..self.game.num_players() {
-                // let util = Self::vanilla_cfr(
-                //     &self.game,
-                //     &initial_state,
-                //     p,
-                //     1.0,
-                //     1.0,
-                //     &mut self.strategies,
-                // );
-                let util = policy.update_player_strategy(&initial_state, p);
-                cumulative_utility[p] += util;
+        let num_players = self.game.num_players();
+        let mut timer = Instant::now();
+        let end = self.iteration + rounds;
+        while self.iteration < end {
+            let batch_start = self.iteration;
+            let batch_end = usize::min(batch_start + self.config.print_steps, end);
+            let game = &self.game;
+            let solver = &self.solver;
+            let seed = self.seed;
+            let batch_utility = pool.install(|| {
+                (batch_start..batch_end)
+                    .into_par_iter()
+                    .map(|i| {
+                        let mut rng = round_rng(seed, i + 1);
+                        let initial_state = game.start_with_seed(rng.gen());
+                        (0..num_players)
+                            .map(|p| {
+                                solver.update_player_strategy(&initial_state, p, i + 1, &mut rng)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .reduce(
+                        || vec![A::zero(); num_players],
+                        |a, b| a.iter().zip(b.iter()).map(|(x, y)| *x + *y).collect(),
+                    )
+            });
+            for (total, util) in self.cumulative_utility.iter_mut().zip(batch_utility) {
+                *total += util;
+            }
+            self.iteration = batch_end;
+
+            // Checks if a multiple of the provided number of steps was reached during the batch
+            let crossed = |steps: usize| batch_end / steps > batch_start / steps;
+
+            println!("Round: {}", batch_end);
+            println!("\tUtility (Cumulative): {:?}", self.cumulative_utility);
+            println!("\tVisited States: {}", self.solver.seen_states());
+
+            let mut exploitability = None;
+            if let Some(eval_steps) = self.config.eval_steps {
+                if crossed(eval_steps) {
+                    let strategies = self.solver.strategies_snapshot();
+                    let report = eval::exploitability(&self.game, &strategies);
+                    println!("\tExploitability: {}", report.exploitability);
+                    println!("\tNashConv: {}", report.nash_conv);
+                    exploitability = Some(report.exploitability);
+                    if self.record_exploitability(report.exploitability) {
+                        println!("Saving Best Strategy");
+                        self.training_secs += timer.elapsed().as_secs_f64();
+                        timer = Instant::now();
+                        self.save_checkpoint(
+                            self.config.best_ckpt_path(),
+                            self.solver.name(),
+                            self.solver.params(),
+                            &strategies,
+                        )?;
+                    }
+                }
             }
-            if (i + 1) % print_steps == 0 {
-                println!("Round: {}", i + 1);
-                println!("\tUtility (Cumulative): {:?}", cumulative_utility);
-                println!("\tVisited States: {}", policy.seen_states());
+            if self.metrics.is_some() {
+                let memory_bytes = self.solver.memory_bytes();
+                self.log_metrics(self.solver.seen_states(), memory_bytes, 0, exploitability)?;
             }
 
-            if (i + 1) % ckpt_steps == 0 {
-                println!("Saving Current Strategy");
-                //let path = format!("./strategies/scrabble_{}.ckpt", i + 1);
-                let path = "./strategies/scrabble.ckpt";
-                serialization::save_to_disk(policy.strategies(), path)
+            if let Some(ckpt_steps) = self.config.ckpt_steps {
+                if crossed(ckpt_steps) {
+                    println!("Saving Current Strategy");
+                    self.training_secs += timer.elapsed().as_secs_f64();
+                    timer = Instant::now();
+                    let strategies = self.solver.strategies_snapshot();
+                    self.save_checkpoint(
+                        self.config.ckpt_path(batch_end),
+                        self.solver.name(),
+                        self.solver.params(),
+                        &strategies,
+                    )?;
+                    self.remove_old_checkpoints()?;
+                }
             }
         }
+        self.training_secs += timer.elapsed().as_secs_f64();
         println!("CFR Training Complete");
+        Ok(())
     }
+}
+
+/// Creates the random number generator of the provided round. Each round gets its own generator
+/// derived from the seed, so the randomness of a round does not depend on which thread runs it
+/// or whether training was resumed from a checkpoint
+fn round_rng(seed: u64, iteration: usize) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (iteration as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfr::checkpoint::{read_metadata, CheckpointError};
+    use crate::cfr::config::TrainerConfig;
+    use crate::cfr::eval;
+    use crate::cfr::solvers::{
+        CfrPlusSolver,
+        DiscountedCfrSolver,
+        OutcomeSamplingSolver,
+        ParallelOutcomeSamplingSolver,
+    };
+    use crate::error::Error;
+    use crate::kuhn::{KuhnPoker, KuhnState};
+
+    use super::CFRTrainer;
 
-    
+    #[test]
+    fn test_rejects_invalid_config() {
+        let config = TrainerConfig {
+            print_steps: 0,
+            ..Default::default()
+        };
+        let solver = CfrPlusSolver::<KuhnState, f64>::new(2);
+        let trainer =
+            CFRTrainer::<_, f64, _>::with_config(KuhnPoker::new(), solver, config.clone());
+        assert!(matches!(trainer, Err(Error::Config(_))));
+
+        // Checked before the checkpoint is read
+        let solver = ParallelOutcomeSamplingSolver::<KuhnState, f64>::new(2);
+        let trainer = CFRTrainer::<_, f64, _>::from_checkpoint(
+            KuhnPoker::new(),
+            solver,
+            config,
+            "does_not_exist.ckpt",
+        );
+        assert!(matches!(trainer, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_rejects_checkpoint_of_other_solver() {
+        let config = TrainerConfig {
+            ckpt_steps: Some(10),
+            ckpt_dir: std::env::temp_dir().join("trainer_solver_mismatch_test"),
+            ..Default::default()
+        };
+        let solver = DiscountedCfrSolver::<KuhnState, f64>::new(2, 1.5, 0.0, 2.0);
+        let mut trainer =
+            CFRTrainer::with_config(KuhnPoker::new(), solver, config.clone()).unwrap();
+        trainer.train(10).unwrap();
+
+        let path = config.latest_ckpt().unwrap().unwrap();
+        let other_solver = CFRTrainer::<_, f64, _>::from_checkpoint(
+            KuhnPoker::new(),
+            CfrPlusSolver::<KuhnState, f64>::new(2),
+            config.clone(),
+            &path,
+        );
+        let other_params = CFRTrainer::<_, f64, _>::from_checkpoint(
+            KuhnPoker::new(),
+            DiscountedCfrSolver::<KuhnState, f64>::new(2, 1.0, 1.0, 1.0),
+            config.clone(),
+            &path,
+        );
+        std::fs::remove_dir_all(&config.ckpt_dir).unwrap();
+
+        assert!(matches!(
+            other_solver,
+            Err(Error::Checkpoint(CheckpointError::Mismatch {
+                field: "solver",
+                ..
+            }))
+        ));
+        assert!(matches!(
+            other_params,
+            Err(Error::Checkpoint(CheckpointError::Mismatch {
+                field: "solver parameters",
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let config = TrainerConfig {
+            print_steps: 500,
+            ckpt_steps: Some(500),
+            ckpt_dir: std::env::temp_dir().join("trainer_resume_test"),
+            ckpt_name: "kuhn".to_string(),
+            ..Default::default()
+        };
+        let solver = CfrPlusSolver::<KuhnState, f64>::new(2);
+        let mut trainer =
+            CFRTrainer::with_config(KuhnPoker::new(), solver, config.clone()).unwrap();
+        trainer.train(500).unwrap();
+
+        let mut resumed = CFRTrainer::from_checkpoint(
+            KuhnPoker::new(),
+            CfrPlusSolver::<KuhnState, f64>::new(2),
+            config.clone(),
+            config.latest_ckpt().unwrap().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(resumed.iteration(), 500);
+        assert_eq!(resumed.get_strategies().len(), trainer.get_strategies().len());
+
+        resumed.train(500).unwrap();
+        std::fs::remove_dir_all(&config.ckpt_dir).unwrap();
+        assert_eq!(resumed.iteration(), 1000);
+        let report = eval::exploitability(&KuhnPoker::new(), resumed.get_strategies());
+        assert!(
+            report.exploitability < 5e-3,
+            "Exploitability too high: {}",
+            report.exploitability
+        );
+    }
+
+    #[test]
+    fn test_keeps_recent_and_best_checkpoints() {
+        let config = TrainerConfig {
+            print_steps: 100,
+            ckpt_steps: Some(100),
+            ckpt_dir: std::env::temp_dir().join("trainer_rotation_test"),
+            ckpt_name: "kuhn".to_string(),
+            keep_ckpts: Some(2),
+            keep_best_ckpt: true,
+            eval_steps: Some(100),
+            ..Default::default()
+        };
+        let solver = CfrPlusSolver::<KuhnState, f64>::new(2);
+        let mut trainer =
+            CFRTrainer::with_config(KuhnPoker::new(), solver, config.clone()).unwrap();
+        trainer.train(500).unwrap();
+
+        let ckpts = config.list_ckpts().unwrap();
+        let best = read_metadata(config.best_ckpt_path());
+        let latest = read_metadata(config.ckpt_path(500));
+        std::fs::remove_dir_all(&config.ckpt_dir).unwrap();
+
+        let iterations = ckpts.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+        assert_eq!(iterations, vec![400, 500]);
+        let best = best.unwrap().exploitability.unwrap();
+        let latest = latest.unwrap().exploitability.unwrap();
+        assert!(best <= latest);
+    }
+
+    #[test]
+    fn test_same_seed_replays_training() {
+        let train = |seed| {
+            let config = TrainerConfig {
+                seed: Some(seed),
+                ..Default::default()
+            };
+            let solver = OutcomeSamplingSolver::<KuhnState, f64>::new(2);
+            let mut trainer =
+                CFRTrainer::with_config(KuhnPoker::new(), solver, config).unwrap();
+            trainer.train(1000).unwrap();
+            eval::exploitability(&KuhnPoker::new(), trainer.get_strategies()).exploitability
+        };
+        assert_eq!(train(7), train(7));
+        assert_ne!(train(7), train(8));
+    }
 }
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::io;
+
+use crate::cfr::checkpoint::CheckpointError;
+
+/// Errors that can occur while loading game data or saving and restoring training progress
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written
+    Io(io::Error),
+    /// Binary data could not be encoded or decoded
+    Bincode(bincode::Error),
+    /// A JSON file (i.e. a board or config) could not be parsed
+    Json(serde_json::Error),
+    /// The lexicon could not be built
+    Fst(fst::Error),
+    /// A checkpoint could not be saved or loaded
+    Checkpoint(CheckpointError),
+    /// A strategy file is not in the expected format
+    StrategyFile(String),
+    /// A config or game parameter has a value that cannot be used
+    Config(String),
+    /// The threads used for parallel training could not be started
+    ThreadPool(rayon::ThreadPoolBuildError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Bincode(e) => write!(f, "Serialization error: {}", e),
+            Error::Json(e) => write!(f, "JSON error: {}", e),
+            Error::Fst(e) => write!(f, "Lexicon error: {}", e),
+            Error::Checkpoint(e) => write!(f, "{}", e),
+            Error::StrategyFile(e) => write!(f, "Invalid strategy file: {}", e),
+            Error::Config(e) => write!(f, "Invalid config: {}", e),
+            Error::ThreadPool(e) => write!(f, "Failed to start training threads: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Bincode(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Fst(e) => Some(e),
+            Error::Checkpoint(e) => Some(e),
+            Error::ThreadPool(e) => Some(e),
+            Error::StrategyFile(_) | Error::Config(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Error::Bincode(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<fst::Error> for Error {
+    fn from(e: fst::Error) -> Self {
+        Error::Fst(e)
+    }
+}
+
+impl From<CheckpointError> for Error {
+    fn from(e: CheckpointError) -> Self {
+        Error::Checkpoint(e)
+    }
+}
+
+impl From<rayon::ThreadPoolBuildError> for Error {
+    fn from(e: rayon::ThreadPoolBuildError) -> Self {
+        Error::ThreadPool(e)
+    }
+}
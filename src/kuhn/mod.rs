@@ -0,0 +1,212 @@
+use crate::cfr::state::{Game, GameState};
+
+/// Number of cards in the Kuhn poker deck (Jack, Queen, King)
+const NUM_CARDS: usize = 3;
+
+/// Action that passes (check/fold)
+const PASS: usize = 0;
+/// Action that bets (bet/call)
+const BET: usize = 1;
+
+/// Kuhn poker: a two player game where each player antes 1 chip and is dealt a single private
+/// card from a deck of 3. Players then take turns to either pass or bet 1 additional chip.
+/// The game value for the first player is -1/18.
+#[derive(Debug, Clone)]
+pub struct KuhnState {
+    /// Card dealt to each player. None until the deal has taken place
+    cards: Option<[usize; 2]>,
+    /// Actions taken by the players so far
+    history: Vec<usize>,
+}
+
+impl KuhnState {
+    /// Amount each player has put into the pot
+    fn contributions(&self) -> [f32; 2] {
+        let mut pot = [1.0, 1.0];
+        for (i, &a) in self.history.iter().enumerate() {
+            if a == BET {
+                pot[i % 2] += 1.0;
+            }
+        }
+        pot
+    }
+
+    /// Gets the player that folded (passed after facing a bet), if any
+    fn folded_player(&self) -> Option<usize> {
+        match self.history.as_slice() {
+            [BET, PASS] => Some(1),
+            [PASS, BET, PASS] => Some(0),
+            _ => None,
+        }
+    }
+
+    fn history_key(&self) -> String {
+        self.history
+            .iter()
+            .map(|&a| if a == BET { 'b' } else { 'p' })
+            .collect()
+    }
+}
+
+impl GameState for KuhnState {
+    type Key = String;
+
+    fn active_player(&self) -> usize {
+        self.history.len() % 2
+    }
+
+    fn valid_actions(&self) -> Vec<usize> {
+        vec![PASS, BET]
+    }
+
+    fn state_key(&self) -> Self::Key {
+        match self.cards {
+            Some(cards) => format!("{}{}:{}", cards[0], cards[1], self.history_key()),
+            None => String::new(),
+        }
+    }
+
+    fn information_set_key(&self, player: usize) -> Self::Key {
+        // Players can only observe their own card and the public betting history
+        match self.cards {
+            Some(cards) => format!("{}:{}", cards[player], self.history_key()),
+            None => String::new(),
+        }
+    }
+
+    fn next_state(&self, action: usize) -> Option<Self> {
+        if self.is_terminal() {
+            return None;
+        }
+        let mut history = self.history.clone();
+        history.push(action);
+        Some(KuhnState {
+            cards: self.cards,
+            history,
+        })
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(
+            self.history.as_slice(),
+            [PASS, PASS] | [BET, _] | [PASS, BET, _]
+        )
+    }
+
+    fn is_chance(&self) -> bool {
+        self.cards.is_none()
+    }
+
+    fn chance_outcomes(&self) -> Vec<(usize, f32)> {
+        // Every ordered pair of distinct cards is equally likely
+        let num_deals = NUM_CARDS * (NUM_CARDS - 1);
+        (0..NUM_CARDS * NUM_CARDS)
+            .filter(|o| o / NUM_CARDS != o % NUM_CARDS)
+            .map(|o| (o, 1.0 / num_deals as f32))
+            .collect()
+    }
+
+    fn apply_chance_outcome(&self, outcome: usize) -> Self {
+        KuhnState {
+            cards: Some([outcome / NUM_CARDS, outcome % NUM_CARDS]),
+            history: self.history.clone(),
+        }
+    }
+
+    fn get_reward(&self, player: usize) -> f32 {
+        let pot = self.contributions();
+        let winner = match self.folded_player() {
+            Some(folded) => 1 - folded,
+            None => {
+                let cards = self.cards.unwrap();
+                if cards[0] > cards[1] {
+                    0
+                } else {
+                    1
+                }
+            }
+        };
+        if winner == player {
+            pot[1 - player]
+        } else {
+            -pot[player]
+        }
+    }
+}
+
+pub struct KuhnPoker;
+
+impl KuhnPoker {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Game for KuhnPoker {
+    type State = KuhnState;
+
+    fn num_players(&self) -> usize {
+        2
+    }
+
+    fn num_actions(&self) -> usize {
+        2
+    }
+
+    fn start(&self) -> Self::State {
+        KuhnState {
+            cards: None,
+            history: Vec::new(),
+        }
+    }
+
+    fn reset(&mut self) {}
+
+    fn name(&self) -> String {
+        "kuhn_poker".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cfr::state::{Game, GameState};
+
+    use super::{KuhnPoker, KuhnState, BET, PASS};
+
+    /// Deals the provided cards to each player
+    fn deal(cards: [usize; 2]) -> KuhnState {
+        KuhnPoker::new()
+            .start()
+            .apply_chance_outcome(cards[0] * 3 + cards[1])
+    }
+
+    #[test]
+    fn test_rewards() {
+        // Player 0 has the king and player 1 has the jack
+        let state = deal([2, 0]);
+        let showdown = state.next_state(BET).unwrap().next_state(BET).unwrap();
+        assert!(showdown.is_terminal());
+        assert_eq!(showdown.get_reward(0), 2.0);
+        assert_eq!(showdown.get_reward(1), -2.0);
+
+        let folded = state
+            .next_state(PASS)
+            .unwrap()
+            .next_state(BET)
+            .unwrap()
+            .next_state(PASS)
+            .unwrap();
+        assert!(folded.is_terminal());
+        assert_eq!(folded.get_reward(0), -1.0);
+        assert_eq!(folded.get_reward(1), 1.0);
+    }
+
+    #[test]
+    fn test_information_set_hides_opponent_card() {
+        let a = deal([0, 1]);
+        let b = deal([0, 2]);
+        assert_eq!(a.information_set_key(0), b.information_set_key(0));
+        assert_ne!(a.information_set_key(1), b.information_set_key(1));
+        assert_ne!(a.state_key(), b.state_key());
+    }
+}
@@ -0,0 +1,344 @@
+use crate::cfr::state::{Game, GameState};
+
+/// Number of cards in the deck (Jack, Queen and King in two suits)
+const NUM_CARDS: usize = 6;
+/// Number of betting rounds in the game
+const NUM_ROUNDS: usize = 2;
+/// Maximum number of raises allowed in a single betting round
+const MAX_RAISES: usize = 2;
+/// Amount that is raised in each betting round
+const RAISE_SIZES: [f32; NUM_ROUNDS] = [2.0, 4.0];
+
+/// Action that gives up the hand when facing a raise
+const FOLD: usize = 0;
+/// Action that checks, or calls when facing a raise
+const CALL: usize = 1;
+/// Action that raises by the fixed amount of the current round
+const RAISE: usize = 2;
+
+/// Leduc hold'em: a two player game played with a deck of 6 cards. Each player antes 1 chip and
+/// is dealt a single private card, followed by a round of betting. A public card is then revealed
+/// followed by a second round of betting. At showdown, a player that pairs the public card wins,
+/// otherwise the highest card wins.
+#[derive(Debug, Clone)]
+pub struct LeducState {
+    /// Private card dealt to each player
+    private_cards: Vec<usize>,
+    /// Card shared by both players, revealed after the first round of betting
+    public_card: Option<usize>,
+    /// Current betting round
+    round: usize,
+    /// Actions taken in each betting round
+    history: [Vec<usize>; NUM_ROUNDS],
+    /// Amount each player has put into the pot
+    contributions: [f32; 2],
+    /// Player that folded the hand, if any
+    folded: Option<usize>,
+    /// Currently active player
+    curr_player: usize,
+}
+
+impl LeducState {
+    /// Rank of the card, ignoring the suit
+    fn rank(card: usize) -> usize {
+        card / 2
+    }
+
+    /// Number of raises in the current betting round
+    fn num_raises(&self) -> usize {
+        self.history[self.round]
+            .iter()
+            .filter(|&&a| a == RAISE)
+            .count()
+    }
+
+    /// Checks if the active player has to call an outstanding raise
+    fn facing_raise(&self) -> bool {
+        self.contributions[0] != self.contributions[1]
+    }
+
+    /// Strength of the player's hand at showdown. Pairing the public card beats any high card
+    fn hand_strength(&self, player: usize) -> usize {
+        let rank = Self::rank(self.private_cards[player]);
+        match self.public_card {
+            Some(public) if Self::rank(public) == rank => NUM_CARDS + rank,
+            _ => rank,
+        }
+    }
+
+    fn history_key(&self) -> String {
+        let rounds = self
+            .history
+            .iter()
+            .map(|round| {
+                round
+                    .iter()
+                    .map(|&a| match a {
+                        FOLD => 'f',
+                        CALL => 'c',
+                        _ => 'r',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        rounds.join("/")
+    }
+
+    fn card_key(card: Option<usize>) -> String {
+        match card {
+            Some(card) => Self::rank(card).to_string(),
+            None => "-".to_string(),
+        }
+    }
+}
+
+impl GameState for LeducState {
+    type Key = String;
+
+    fn active_player(&self) -> usize {
+        self.curr_player
+    }
+
+    fn valid_actions(&self) -> Vec<usize> {
+        let mut actions = Vec::new();
+        if self.facing_raise() {
+            actions.push(FOLD);
+        }
+        actions.push(CALL);
+        if self.num_raises() < MAX_RAISES {
+            actions.push(RAISE);
+        }
+        actions
+    }
+
+    fn state_key(&self) -> Self::Key {
+        let cards = self
+            .private_cards
+            .iter()
+            .map(|&c| Self::card_key(Some(c)))
+            .collect::<String>();
+        format!(
+            "{}{}:{}",
+            cards,
+            Self::card_key(self.public_card),
+            self.history_key()
+        )
+    }
+
+    fn information_set_key(&self, player: usize) -> Self::Key {
+        // Players can only observe their own card, the public card and the betting history
+        format!(
+            "{}{}:{}",
+            Self::card_key(self.private_cards.get(player).copied()),
+            Self::card_key(self.public_card),
+            self.history_key()
+        )
+    }
+
+    fn next_state(&self, action: usize) -> Option<Self> {
+        if self.is_terminal() {
+            return None;
+        }
+
+        let mut next = self.clone();
+        let player = self.curr_player;
+        let other = 1 - player;
+        next.history[self.round].push(action);
+        match action {
+            FOLD => next.folded = Some(player),
+            CALL => {
+                next.contributions[player] = self.contributions[other];
+                // Calling (or checking) ends the round unless it was the first action
+                if !self.history[self.round].is_empty() {
+                    next.round += 1;
+                    next.curr_player = 0;
+                    return Some(next);
+                }
+            }
+            _ => {
+                next.contributions[player] = self.contributions[other] + RAISE_SIZES[self.round];
+            }
+        }
+        next.curr_player = other;
+        Some(next)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.folded.is_some() || self.round >= NUM_ROUNDS
+    }
+
+    fn is_chance(&self) -> bool {
+        // Private cards are dealt before the first round and the public card before the second
+        self.private_cards.len() < 2 || (self.round == 1 && self.public_card.is_none())
+    }
+
+    fn chance_outcomes(&self) -> Vec<(usize, f32)> {
+        let remaining = (0..NUM_CARDS)
+            .filter(|c| !self.private_cards.contains(c) && self.public_card != Some(*c))
+            .collect::<Vec<_>>();
+        let prob = 1.0 / remaining.len() as f32;
+        remaining.into_iter().map(|c| (c, prob)).collect()
+    }
+
+    fn apply_chance_outcome(&self, outcome: usize) -> Self {
+        let mut next = self.clone();
+        if self.private_cards.len() < 2 {
+            next.private_cards.push(outcome);
+        } else {
+            next.public_card = Some(outcome);
+        }
+        next
+    }
+
+    fn get_reward(&self, player: usize) -> f32 {
+        let other = 1 - player;
+        let winner = match self.folded {
+            Some(folded) => Some(1 - folded),
+            None => {
+                let strength = self.hand_strength(player);
+                let other_strength = self.hand_strength(other);
+                if strength > other_strength {
+                    Some(player)
+                } else if strength < other_strength {
+                    Some(other)
+                } else {
+                    None
+                }
+            }
+        };
+        match winner {
+            Some(w) if w == player => self.contributions[other],
+            Some(_) => -self.contributions[player],
+            // The pot is split on a tie
+            None => 0.0,
+        }
+    }
+}
+
+pub struct LeducHoldem;
+
+impl LeducHoldem {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Game for LeducHoldem {
+    type State = LeducState;
+
+    fn num_players(&self) -> usize {
+        2
+    }
+
+    fn num_actions(&self) -> usize {
+        3
+    }
+
+    fn start(&self) -> Self::State {
+        LeducState {
+            private_cards: Vec::new(),
+            public_card: None,
+            round: 0,
+            history: [Vec::new(), Vec::new()],
+            contributions: [1.0, 1.0],
+            folded: None,
+            curr_player: 0,
+        }
+    }
+
+    fn reset(&mut self) {}
+
+    fn name(&self) -> String {
+        "leduc_holdem".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::cfr::eval;
+    use crate::cfr::solvers::{CfrPlusSolver, Solver};
+    use crate::cfr::state::{Game, GameState};
+
+    use super::{LeducHoldem, LeducState, CALL, FOLD, RAISE};
+
+    /// Deals the provided private cards to each player
+    fn deal(cards: [usize; 2]) -> LeducState {
+        LeducHoldem::new()
+            .start()
+            .apply_chance_outcome(cards[0])
+            .apply_chance_outcome(cards[1])
+    }
+
+    #[test]
+    fn test_valid_actions() {
+        let state = deal([0, 2]);
+        assert!(!state.is_chance());
+        assert_eq!(state.valid_actions(), vec![CALL, RAISE]);
+
+        let state = state.next_state(RAISE).unwrap();
+        assert_eq!(state.valid_actions(), vec![FOLD, CALL, RAISE]);
+
+        // Only two raises are allowed per round
+        let state = state.next_state(RAISE).unwrap();
+        assert_eq!(state.valid_actions(), vec![FOLD, CALL]);
+    }
+
+    #[test]
+    fn test_fold_reward() {
+        let state = deal([0, 2])
+            .next_state(RAISE)
+            .unwrap()
+            .next_state(FOLD)
+            .unwrap();
+        assert!(state.is_terminal());
+        assert_eq!(state.get_reward(0), 1.0);
+        assert_eq!(state.get_reward(1), -1.0);
+    }
+
+    #[test]
+    fn test_pair_wins_showdown() {
+        // Player 0 holds a jack and player 1 holds a king
+        let state = deal([0, 4]);
+        let state = state.next_state(CALL).unwrap().next_state(CALL).unwrap();
+        assert!(state.is_chance());
+
+        // The public jack pairs player 0
+        let state = state.apply_chance_outcome(1);
+        let state = state.next_state(RAISE).unwrap().next_state(CALL).unwrap();
+        assert!(state.is_terminal());
+        assert_eq!(state.get_reward(0), 5.0);
+        assert_eq!(state.get_reward(1), -5.0);
+    }
+
+    #[test]
+    fn test_information_set_hides_opponent_card() {
+        let a = deal([0, 2]);
+        let b = deal([1, 4]);
+        assert_eq!(a.information_set_key(0), b.information_set_key(0));
+        assert_ne!(a.information_set_key(1), b.information_set_key(1));
+    }
+
+    #[test]
+    fn test_cfr_plus_reduces_exploitability() {
+        let game = LeducHoldem::new();
+        let initial_state = game.start();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut solver = CfrPlusSolver::<LeducState, f64>::new(game.num_actions());
+
+        let initial = eval::exploitability(&game, solver.strategies()).exploitability;
+        for i in 0..100 {
+            for p in 0..game.num_players() {
+                solver.update_player_strategy(&initial_state, p, i + 1, &mut rng);
+            }
+        }
+        // Suits never matter, so there are 288 information sets rather than the usual 936
+        assert_eq!(solver.seen_states(), 288);
+
+        let trained = eval::exploitability(&game, solver.strategies()).exploitability;
+        assert!(trained < initial);
+        assert!(trained < 0.05, "Exploitability too high: {}", trained);
+    }
+}
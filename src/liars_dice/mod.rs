@@ -0,0 +1,301 @@
+use crate::cfr::state::{Game, GameState};
+use crate::error::{Error, Result};
+
+/// Liar's Dice: a two player game where each player rolls a number of dice that are hidden from
+/// their opponent. Players take turns making increasingly higher bids on the number of dice across
+/// both players that show a given face, or calling "liar" on the previous bid. If the bid is met
+/// the bidder wins, otherwise the player that called liar wins.
+///
+/// Bids are mapped to actions in increasing order, so bid `b` is a claim that at least
+/// `b / num_faces + 1` dice show face `b % num_faces`. The final action calls liar
+#[derive(Debug, Clone)]
+pub struct LiarsDiceState {
+    /// Number of dice rolled by each player
+    num_dice: [usize; 2],
+    /// Number of faces on each die
+    num_faces: usize,
+    /// Dice rolled by each player so far
+    dice: [Vec<usize>; 2],
+    /// Bids made by the players so far
+    bids: Vec<usize>,
+    /// Whether the last bid has been challenged
+    challenged: bool,
+}
+
+impl LiarsDiceState {
+    /// Action that calls liar on the previous bid
+    fn liar_action(&self) -> usize {
+        (self.num_dice[0] + self.num_dice[1]) * self.num_faces
+    }
+
+    /// Player that is currently rolling a die, if the dice have not been rolled yet
+    fn rolling_player(&self) -> Option<usize> {
+        (0..2).find(|&p| self.dice[p].len() < self.num_dice[p])
+    }
+
+    /// Checks if the last bid is met by the dice of both players
+    fn bid_met(&self, bid: usize) -> bool {
+        let quantity = bid / self.num_faces + 1;
+        let face = bid % self.num_faces;
+        let count = self.dice.iter().flatten().filter(|&&d| d == face).count();
+        count >= quantity
+    }
+
+    fn dice_key(&self, player: usize) -> String {
+        // The order the dice were rolled in cannot be observed
+        let mut dice = self.dice[player].clone();
+        dice.sort_unstable();
+        // Faces are separated so multi-digit faces cannot run into each other
+        let dice = dice.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        dice.join(",")
+    }
+
+    fn bids_key(&self) -> String {
+        let bids = self.bids.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+        bids.join(",")
+    }
+}
+
+impl GameState for LiarsDiceState {
+    type Key = String;
+
+    fn active_player(&self) -> usize {
+        self.bids.len() % 2
+    }
+
+    fn valid_actions(&self) -> Vec<usize> {
+        // Every bid has to be higher than the previous one and liar can only be called on a bid
+        let liar = self.liar_action();
+        match self.bids.last() {
+            Some(&last) => (last + 1..=liar).collect(),
+            None => (0..liar).collect(),
+        }
+    }
+
+    fn state_key(&self) -> Self::Key {
+        format!(
+            "{}|{}:{}",
+            self.dice_key(0),
+            self.dice_key(1),
+            self.bids_key()
+        )
+    }
+
+    fn information_set_key(&self, player: usize) -> Self::Key {
+        // Players can only observe their own dice and the bids made so far
+        format!("{}:{}", self.dice_key(player), self.bids_key())
+    }
+
+    fn next_state(&self, action: usize) -> Option<Self> {
+        if self.is_terminal() {
+            return None;
+        }
+        let mut next = self.clone();
+        if action == self.liar_action() {
+            next.challenged = true;
+        } else {
+            next.bids.push(action);
+        }
+        Some(next)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.challenged
+    }
+
+    fn is_chance(&self) -> bool {
+        self.rolling_player().is_some()
+    }
+
+    fn chance_outcomes(&self) -> Vec<(usize, f32)> {
+        let prob = 1.0 / self.num_faces as f32;
+        (0..self.num_faces).map(|f| (f, prob)).collect()
+    }
+
+    fn apply_chance_outcome(&self, outcome: usize) -> Self {
+        let mut next = self.clone();
+        if let Some(player) = self.rolling_player() {
+            next.dice[player].push(outcome);
+        }
+        next
+    }
+
+    fn get_reward(&self, player: usize) -> f32 {
+        // The player that made the last bid is the one that did not call liar
+        let bidder = (self.bids.len() + 1) % 2;
+        let winner = match self.bids.last() {
+            Some(&bid) if self.bid_met(bid) => bidder,
+            _ => 1 - bidder,
+        };
+        if winner == player {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+pub struct LiarsDice {
+    /// Number of dice rolled by each player
+    num_dice: [usize; 2],
+    /// Number of faces on each die
+    num_faces: usize,
+}
+
+impl LiarsDice {
+    /// Creates the game. Fails if the dice have no faces or if no dice are rolled at all, since
+    /// there would be nothing to bid on
+    pub fn new(num_dice: [usize; 2], num_faces: usize) -> Result<Self> {
+        if num_faces == 0 {
+            return Err(Error::Config(
+                "Liar's dice needs dice with at least 1 face".to_string(),
+            ));
+        }
+        if num_dice[0] + num_dice[1] == 0 {
+            return Err(Error::Config(
+                "Liar's dice needs at least 1 die to be rolled".to_string(),
+            ));
+        }
+        Ok(Self {
+            num_dice,
+            num_faces,
+        })
+    }
+}
+
+impl Game for LiarsDice {
+    type State = LiarsDiceState;
+
+    fn num_players(&self) -> usize {
+        2
+    }
+
+    fn num_actions(&self) -> usize {
+        // One action per bid plus calling liar
+        (self.num_dice[0] + self.num_dice[1]) * self.num_faces + 1
+    }
+
+    fn start(&self) -> Self::State {
+        LiarsDiceState {
+            num_dice: self.num_dice,
+            num_faces: self.num_faces,
+            dice: [Vec::new(), Vec::new()],
+            bids: Vec::new(),
+            challenged: false,
+        }
+    }
+
+    fn reset(&mut self) {}
+
+    fn name(&self) -> String {
+        "liars_dice".to_string()
+    }
+
+    fn params(&self) -> String {
+        format!("num_dice={:?}, num_faces={}", self.num_dice, self.num_faces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::cfr::eval;
+    use crate::cfr::solvers::{CfrPlusSolver, Solver};
+    use crate::cfr::state::{Game, GameState};
+    use crate::error::Error;
+
+    use super::{LiarsDice, LiarsDiceState};
+
+    /// Rolls the provided dice for each player
+    fn roll(game: &LiarsDice, dice: &[usize]) -> LiarsDiceState {
+        dice.iter().fold(game.start(), |state, &d| {
+            assert!(state.is_chance());
+            state.apply_chance_outcome(d)
+        })
+    }
+
+    #[test]
+    fn test_valid_actions() {
+        let game = LiarsDice::new([1, 1], 3).unwrap();
+        assert_eq!(game.num_actions(), 7);
+
+        // Liar cannot be called before any bid has been made
+        let state = roll(&game, &[0, 2]);
+        assert!(!state.is_chance());
+        assert_eq!(state.valid_actions(), vec![0, 1, 2, 3, 4, 5]);
+
+        let state = state.next_state(4).unwrap();
+        assert_eq!(state.active_player(), 1);
+        assert_eq!(state.valid_actions(), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_rewards() {
+        let game = LiarsDice::new([2, 1], 3).unwrap();
+        let state = roll(&game, &[1, 2, 1]);
+
+        // Player 0 bids two 1s which is met, so calling liar loses
+        let met = state.next_state(4).unwrap().next_state(9).unwrap();
+        assert!(met.is_terminal());
+        assert_eq!(met.get_reward(0), 1.0);
+        assert_eq!(met.get_reward(1), -1.0);
+
+        // Player 1 raises to two 2s which is not met
+        let unmet = state
+            .next_state(4)
+            .unwrap()
+            .next_state(5)
+            .unwrap()
+            .next_state(9)
+            .unwrap();
+        assert!(unmet.is_terminal());
+        assert_eq!(unmet.get_reward(0), 1.0);
+        assert_eq!(unmet.get_reward(1), -1.0);
+    }
+
+    #[test]
+    fn test_information_set_hides_opponent_dice() {
+        let game = LiarsDice::new([2, 2], 6).unwrap();
+        let a = roll(&game, &[3, 1, 4, 4]);
+        let b = roll(&game, &[1, 3, 5, 2]);
+        assert_eq!(a.information_set_key(0), b.information_set_key(0));
+        assert_ne!(a.information_set_key(1), b.information_set_key(1));
+        assert_ne!(a.state_key(), b.state_key());
+    }
+
+    #[test]
+    fn test_keys_separate_multi_digit_faces() {
+        let game = LiarsDice::new([2, 1], 12).unwrap();
+        let state = roll(&game, &[11, 1, 10]).next_state(3).unwrap();
+        assert_eq!(state.information_set_key(0), "1,11:3");
+        assert_eq!(state.state_key(), "1,11|10:3");
+    }
+
+    #[test]
+    fn test_cfr_plus_reduces_exploitability() {
+        let game = LiarsDice::new([1, 1], 3).unwrap();
+        let initial_state = game.start();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut solver = CfrPlusSolver::<LiarsDiceState, f64>::new(game.num_actions());
+
+        let initial = eval::exploitability(&game, solver.strategies()).exploitability;
+        for i in 0..200 {
+            for p in 0..game.num_players() {
+                solver.update_player_strategy(&initial_state, p, i + 1, &mut rng);
+            }
+        }
+
+        let trained = eval::exploitability(&game, solver.strategies()).exploitability;
+        assert!(trained < initial);
+        assert!(trained < 0.05, "Exploitability too high: {}", trained);
+    }
+
+    #[test]
+    fn test_rejects_games_without_bids() {
+        assert!(matches!(LiarsDice::new([1, 1], 0), Err(Error::Config(_))));
+        assert!(matches!(LiarsDice::new([0, 0], 6), Err(Error::Config(_))));
+        assert!(LiarsDice::new([0, 1], 6).is_ok());
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::rc::Rc;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use cfr::node::StateNode;
 use fst::SetBuilder;
@@ -12,8 +13,22 @@ use scrabble::ScrabbleUI;
 use utils::serialization;
 #[macro_use]
 extern crate text_io;
+use crate::cfr::config::{SolverConfig, TrainerConfig};
 use crate::cfr::state::{Game, GameState};
+use crate::cfr::solvers::{
+    Baseline,
+    CfrPlusSolver,
+    DiscountedCfrSolver,
+    ExternalSamplingSolver,
+    OutcomeSamplingSolver,
+    ParallelOutcomeSamplingSolver,
+    Solver,
+    VanillaCfrSolver,
+};
+use crate::cfr::strategy_file::MappedStrategies;
 use crate::cfr::CFRTrainer;
+use crate::error::{Error, Result};
+use crate::kuhn::{KuhnPoker, KuhnState};
 use crate::scrabble::bag::Bag;
 use crate::scrabble::board::ScrabbleBoard;
 use crate::scrabble::rack::Rack;
@@ -22,14 +37,25 @@ use crate::scrabble::util::Letter;
 use crate::tictactoe::TicTacToe;
 
 mod cfr;
+mod error;
+mod kuhn;
+mod leduc;
+mod liars_dice;
 mod scrabble;
 mod tictactoe;
 mod utils;
 
-fn play_tictactoe() {
+fn play_tictactoe() -> Result<()> {
     let game = TicTacToe::new(3);
-    let mut trainer = CFRTrainer::new(game);
-    trainer.train(1000000, 10000, 100);
+    let solver = OutcomeSamplingSolver::<_, f32>::new(game.num_actions());
+    let config = TrainerConfig {
+        print_steps: 10000,
+        ckpt_steps: Some(100),
+        ckpt_name: "tictactoe".to_string(),
+        ..Default::default()
+    };
+    let mut trainer = CFRTrainer::with_config(game, solver, config)?;
+    trainer.train(1000000)?;
 
     let strat = trainer.get_strategies();
     println!("Number of Strategies: {}", strat.len());
@@ -44,19 +70,14 @@ fn play_tictactoe() {
             state = state.next_state(option).unwrap();
 
             if !state.is_terminal() {
-                let key = state.state_key();
+                let key = state.information_set_key(state.active_player());
                 let node = strat.get(&key).unwrap();
-                let mut avg_strat = node.get_average_strategy();
-                let valid_actions = state.valid_actions();
-                for i in 0..avg_strat.len() {
-                    if !valid_actions.contains(&i) {
-                        avg_strat[i] = 0.0;
-                    }
-                }
-                println!("Strategy: {:?}", avg_strat.iter().collect::<Vec<_>>());
+                let avg_strat = node.get_average_strategy();
+                let action_probs = node.actions().iter().zip(&avg_strat).collect::<Vec<_>>();
+                println!("Strategy: {:?}", action_probs);
                 let dist = WeightedIndex::new(avg_strat).unwrap();
                 let mut rng = rand::thread_rng();
-                let selected_action = dist.sample(&mut rng);
+                let selected_action = node.actions()[dist.sample(&mut rng)];
 
                 state = state.next_state(selected_action).unwrap();
             }
@@ -73,12 +94,37 @@ fn play_tictactoe() {
     }
 }
 
-fn read_vocabulary() -> Vec<String> {
-    let file = File::open("words.txt").unwrap();
+/// Trains Kuhn poker with the named solver, logging how exploitable the average strategy is as
+/// training goes on
+fn train_kuhn(solver: &str) -> Result<()> {
+    let num_actions = KuhnPoker::new().num_actions();
+    match solver {
+        "vanilla" => run_kuhn(VanillaCfrSolver::new(num_actions)),
+        "cfr_plus" => run_kuhn(CfrPlusSolver::new(num_actions)),
+        "discounted" => run_kuhn(DiscountedCfrSolver::new(num_actions, 1.5, 0.0, 2.0)),
+        "external_sampling" => run_kuhn(ExternalSamplingSolver::new(num_actions)),
+        "outcome_sampling" => run_kuhn(OutcomeSamplingSolver::new(num_actions)),
+        _ => Err(Error::Config(format!("Unknown solver '{}'", solver))),
+    }
+}
+
+fn run_kuhn<S: Solver<KuhnState, f64>>(solver: S) -> Result<()> {
+    let config = TrainerConfig {
+        print_steps: 10000,
+        eval_steps: Some(10000),
+        ckpt_name: "kuhn".to_string(),
+        ..Default::default()
+    };
+    let mut trainer = CFRTrainer::with_config(KuhnPoker::new(), solver, config)?;
+    trainer.train(100000)
+}
+
+fn read_vocabulary() -> Result<Vec<String>> {
+    let file = File::open("words.txt")?;
     let reader = BufReader::new(file);
     let mut words = Vec::new();
     for line in reader.lines() {
-        let word = line.unwrap().to_uppercase();
+        let word = line?.to_uppercase();
         if word.chars().count() < 2 {
             continue;
         }
@@ -86,7 +132,7 @@ fn read_vocabulary() -> Vec<String> {
     }
 
     words.sort_unstable();
-    words
+    Ok(words)
 }
 
 /// Builds the scrabble dictionary into a GADDAG. The fst crate only constructs a 
@@ -95,34 +141,87 @@ fn build_dictionary(vocab: Vec<String>) {
     
 }
 
-fn train_scrabble() {
-    let words = read_vocabulary();
+fn train_scrabble() -> Result<()> {
+    let words = read_vocabulary()?;
 
     println!("Number of Words: {}", words.len());
 
     let mut build = SetBuilder::memory();
-    build.extend_iter(words).unwrap();
+    build.extend_iter(words)?;
     let vocab = build.into_set();
 
-    let game = ScrabbleGame::new(2, Rc::new(vocab));
-    let mut trainer = CFRTrainer::<_, f32>::new(game);
-    trainer.train(10000, 10, 1000);
+    let game = ScrabbleGame::new(2, Arc::new(vocab))?;
+    let solver_config = SolverConfig {
+        baseline: Baseline::Learned(0.5),
+        ..Default::default()
+    };
+    let solver =
+        ParallelOutcomeSamplingSolver::<_, f32>::with_config(game.num_actions(), solver_config)?;
+    let config = TrainerConfig {
+        print_steps: 10,
+        ckpt_steps: Some(1000),
+        ckpt_name: "scrabble".to_string(),
+        metrics_path: Some(PathBuf::from("./scrabble_metrics.csv")),
+        ..Default::default()
+    };
+    // Pick up where the last run left off if it was interrupted
+    let mut trainer = if let Some(path) = config.latest_ckpt()? {
+        CFRTrainer::from_checkpoint(game, solver, config, path)?
+    } else {
+        CFRTrainer::with_config(game, solver, config)?
+    };
+    trainer.train_parallel(10000)?;
+    // The agent plays from a memory-mapped copy of the strategies so it starts instantly
+    MappedStrategies::write(
+        "./strategies/scrabble.strat",
+        &trainer.get_strategies_snapshot(),
+    )
 }
 
-fn play_scrabble() {
-    let words = read_vocabulary();
+/// Writes the strategy file the agent plays from using the latest scrabble checkpoint, for
+/// runs that were stopped before they could write it themselves
+fn export_scrabble_strategies() -> Result<()> {
+    let config = TrainerConfig {
+        ckpt_name: "scrabble".to_string(),
+        ..Default::default()
+    };
+    if let Some(path) = config.latest_ckpt()? {
+        let num_states = MappedStrategies::convert_checkpoint::<u128, f32, _, _>(
+            path,
+            "./strategies/scrabble.strat",
+        )?;
+        println!("Exported {} states", num_states);
+    } else {
+        println!("No scrabble checkpoint to export");
+    }
+    Ok(())
+}
+
+fn play_scrabble() -> Result<()> {
+    let words = read_vocabulary()?;
     let mut build = SetBuilder::memory();
-    build.extend_iter(words).unwrap();
+    build.extend_iter(words)?;
     let vocab = build.into_set();
-    let vocab = Rc::new(vocab);
-    let game = ScrabbleGame::new(2, vocab);
+    let vocab = Arc::new(vocab);
+    let game = ScrabbleGame::new(2, vocab)?;
     ScrabbleUI::run(game).expect("Something went wrong");
+    Ok(())
 }
 
 fn main() {
-    //play_tictactoe();
-    //train_scrabble();
-    play_scrabble();
+    //let result = play_tictactoe();
+    //let result = train_scrabble();
+    //let result = export_scrabble_strategies();
+    // `kuhn <solver>` compares the solvers on Kuhn poker, otherwise scrabble is played
+    let args = std::env::args().collect::<Vec<_>>();
+    let result = match args.get(1).map(String::as_str) {
+        Some("kuhn") => train_kuhn(args.get(2).map_or("cfr_plus", String::as_str)),
+        _ => play_scrabble(),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
     
 }
 
@@ -2,45 +2,94 @@ use std::collections::HashMap;
 use std::path::Path;
 
 use ndarray_rand::rand_distr::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
+use crate::cfr::checkpoint::Checkpoint;
 use crate::cfr::node::StateNode;
 use crate::cfr::state::GameState;
-use crate::utils::serialization;
+use crate::cfr::strategy_file::MappedStrategies;
+use crate::error::Result;
 
 use super::state::ScrabbleState;
 
+/// Where the agent looks up the strategy of each state
+enum AgentStrategies {
+    /// Strategies decoded from a checkpoint
+    Loaded(HashMap<u128, StateNode<f32>>),
+    /// Average strategies read lazily from a memory-mapped strategy file
+    Mapped(MappedStrategies),
+}
+
+impl AgentStrategies {
+    /// Gets the probability of each action the average strategy of the state covers
+    fn average_strategy(&self, key: &u128) -> Option<Vec<(usize, f32)>> {
+        match self {
+            AgentStrategies::Loaded(strategies) => strategies.get(key).map(|node| {
+                node.actions()
+                    .iter()
+                    .copied()
+                    .zip(node.get_average_strategy())
+                    .collect()
+            }),
+            AgentStrategies::Mapped(strategies) => strategies.average_strategy(key),
+        }
+    }
+}
+
 pub struct ScrabbleAgent {
-    strategies: HashMap<String, StateNode<f32>>,
+    strategies: AgentStrategies,
+    /// Random number generator used to sample actions from the strategies
+    rng: StdRng,
 }
 
 impl ScrabbleAgent {
-    pub fn new(strategies: HashMap<String, StateNode<f32>>) -> Self {
-        Self { strategies }
+    pub fn new(strategies: HashMap<u128, StateNode<f32>>) -> Self {
+        Self::with_seed(strategies, rand::random())
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+    /// Creates an agent whose sampled actions are decided by the provided seed
+    pub fn with_seed(strategies: HashMap<u128, StateNode<f32>>, seed: u64) -> Self {
+        Self {
+            strategies: AgentStrategies::Loaded(strategies),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         println!("Loading Agent Strategy");
-        let strategies = serialization::load_from_disk(path);
+        let checkpoint = Checkpoint::load(path)?;
         println!("Strategy Loaded");
-        Self::new(strategies)
+        Ok(Self::new(checkpoint.strategies))
+    }
+
+    /// Creates an agent that plays the strategies in a strategy file. The file is memory-mapped
+    /// and states are only read when they are played, so this returns instantly
+    pub fn from_strategy_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_strategy_file_with_seed(path, rand::random())
+    }
+
+    /// Creates an agent that plays the strategies in a strategy file, with its sampled actions
+    /// decided by the provided seed
+    pub fn from_strategy_file_with_seed<P: AsRef<Path>>(path: P, seed: u64) -> Result<Self> {
+        let strategies = MappedStrategies::open(path)?;
+        println!("Mapped strategies of {} states", strategies.len());
+        Ok(Self {
+            strategies: AgentStrategies::Mapped(strategies),
+            rng: StdRng::seed_from_u64(seed),
+        })
     }
 
-    pub fn get_action(&self, state: &ScrabbleState) -> usize {
-        let state_key = state.state_key();
+    pub fn get_action(&mut self, state: &ScrabbleState) -> usize {
+        let state_key = state.information_set_key(state.active_player());
         let valid_moves = state.valid_actions();
-        if let Some(node) = self.strategies.get(&state_key) {
-            let mut avg_strat = node.get_average_strategy();
-            for i in 0..avg_strat.len() {
-                if !valid_moves.contains(&i) {
-                    avg_strat[i] = 0.0;
-                }
-            }
-            let dist = WeightedIndex::new(avg_strat.view()).unwrap();
-            let mut rng = rand::thread_rng();
-            let selected_action = dist.sample(&mut rng);
+        if let Some(mut avg_strat) = self.strategies.average_strategy(&state_key) {
+            avg_strat.retain(|(a, _)| valid_moves.contains(a));
+            let dist = WeightedIndex::new(avg_strat.iter().map(|(_, p)| p)).unwrap();
+            let (selected_action, prob) = avg_strat[dist.sample(&mut self.rng)];
             println!(
                 "Selected Action: {} with probability {}",
-                selected_action, avg_strat[selected_action]
+                selected_action, prob
             );
             return selected_action;
         }
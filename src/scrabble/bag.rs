@@ -1,7 +1,5 @@
 use std::collections::HashMap;
 
-use rand::prelude::SliceRandom;
-
 use super::util::Letter;
 
 #[derive(Debug, Clone)]
@@ -12,12 +10,19 @@ pub struct Bag {
     amts: [usize; 27],
     values: [i32; 27],
     scores: HashMap<Letter, i32>,
-    random: bool,
     pub distribution: Vec<Letter>,
 }
 
+impl Default for Bag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Bag {
-    pub fn default() -> Bag {
+    /// Creates a full bag. Tiles are drawn through the chance nodes of the game rather than from
+    /// a shuffled order, so the bag is kept sorted
+    pub fn new() -> Bag {
         let mut bag = Bag {
             alph: [
                 'A'.into(),
@@ -56,7 +61,6 @@ impl Bag {
             ],
             scores: HashMap::new(),
             distribution: Vec::new(),
-            random: true,
         };
 
         for (i, &c) in bag.alph.iter().enumerate() {
@@ -69,15 +73,12 @@ impl Bag {
             }
         }
 
-        bag.distribution.shuffle(&mut rand::thread_rng());
-
         bag
     }
 
     pub fn new_with_order(order: &Vec<Letter>) -> Bag {
-        let mut b = Bag::default();
+        let mut b = Bag::new();
         b.distribution = order.to_vec();
-        b.random = false;
         b
     }
 
@@ -92,22 +93,28 @@ impl Bag {
         }
     }
 
-    pub fn draw_tiles(&mut self, n: usize) -> Vec<Letter> {
-        let tiles: Vec<Letter>;
-        if self.random {
-            tiles = self
-                .distribution
-                .choose_multiple(&mut rand::thread_rng(), n)
-                .cloned()
-                .collect();
-        } else {
-            tiles = self.distribution.iter().take(n).cloned().collect();
-        }
-        for i in tiles.iter() {
-            if let Some(pos) = self.distribution.iter().position(|&x| *i == x) {
-                self.distribution.remove(pos);
-            }
-        }
-        tiles
+    /// Gets the probability of drawing each letter that is left in the bag, keyed by the index
+    /// of the letter in the alphabet
+    pub fn tile_probabilities(&self) -> Vec<(usize, f32)> {
+        let total = self.distribution.len() as f32;
+        self.alph
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (i, self.distribution.iter().filter(|&x| x == l).count()))
+            .filter(|&(_, count)| count > 0)
+            .map(|(i, count)| (i, count as f32 / total))
+            .collect()
+    }
+
+    /// Removes a tile of the letter with the provided index in the alphabet from the bag
+    pub fn take_tile(&mut self, letter_id: usize) -> Letter {
+        let letter = self.alph[letter_id];
+        let pos = self
+            .distribution
+            .iter()
+            .position(|&x| x == letter)
+            .expect("Letter is not in the bag");
+        self.distribution.remove(pos);
+        letter
     }
 }
@@ -7,6 +7,8 @@ use std::path::Path;
 
 use fst::{IntoStreamer, Set, Streamer};
 
+use crate::error::Result;
+
 use super::bag::Bag;
 use super::constraint::grid::ConstraintGrid;
 use super::constraint::ConstraintQuery;
@@ -49,12 +51,13 @@ impl ScrabbleBoard {
         }
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
-        let mut file = File::open(path.as_ref()).unwrap();
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path.as_ref())?;
         let mut data = String::new();
-        file.read_to_string(&mut data).unwrap();
+        file.read_to_string(&mut data)?;
 
-        let raw_board: Vec<Vec<String>> = serde_json::from_str(&data).unwrap();
+        // Boards with the wrong dimensions fail to parse rather than panicking below
+        let raw_board: [[String; BOARD_SIZE]; BOARD_SIZE] = serde_json::from_str(&data)?;
         let mut state = vec![vec![Tile::Empty; BOARD_SIZE]; BOARD_SIZE];
 
         for i in 0..BOARD_SIZE {
@@ -72,11 +75,11 @@ impl ScrabbleBoard {
             }
         }
 
-        Self {
+        Ok(Self {
             state,
             blanks: HashSet::new(),
             placements: vec![],
-        }
+        })
     }
 
     /// Places the current word on the board. Assumes that the word is a valid placement.
@@ -6,17 +6,19 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
+use crate::error::Result;
+
 use super::BOARD_SIZE;
 
 static SEP: u8 = ',' as u8;
 static SEP_STR: &str = ",";
 
-fn read_word_file<P: AsRef<Path>>(path: P) -> Vec<String> {
-    let file = File::open(path).unwrap();
+fn read_word_file<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut words = Vec::new();
     for line in reader.lines() {
-        let word = line.unwrap().to_uppercase();
+        let word = line?.to_uppercase();
         if word.chars().count() < 2 {
             continue;
         }
@@ -24,14 +26,14 @@ fn read_word_file<P: AsRef<Path>>(path: P) -> Vec<String> {
     }
 
     words.sort_unstable();
-    words
+    Ok(words)
 }
 
 /// Builds the scrabble dictionary into a GADDAG. The fst crate only constructs a
 /// DAG, so we need to handle the case for suffix searches as well
 /// Implementation adopted from https://amedee.me/2020/11/04/fst-gaddag/
-fn build_entries_sorted<P: AsRef<Path>>(vocab_path: P) -> BTreeSet<Vec<u8>> {
-    let words = read_word_file(vocab_path);
+fn build_entries_sorted<P: AsRef<Path>>(vocab_path: P) -> Result<BTreeSet<Vec<u8>>> {
+    let words = read_word_file(vocab_path)?;
 
     let mut entries: BTreeSet<Vec<u8>> = BTreeSet::new();
     // Only need to allocate a max of BOARD_SIZE characters
@@ -65,7 +67,7 @@ fn build_entries_sorted<P: AsRef<Path>>(vocab_path: P) -> BTreeSet<Vec<u8>> {
             entries.insert(new_word.iter().cloned().collect());
         }
     }
-    entries
+    Ok(entries)
 }
 
 /// Implementation of GADDAG for efficiently scanning scrabble words
@@ -75,10 +77,10 @@ pub struct Gaddag {
 }
 
 impl Gaddag {
-    pub fn build_from_file<P: AsRef<Path>>(vocab_path: P) -> Self {
-        let entries = build_entries_sorted(vocab_path);
-        let dict = Set::from_iter(entries).unwrap();
-        Self { dict }
+    pub fn build_from_file<P: AsRef<Path>>(vocab_path: P) -> Result<Self> {
+        let entries = build_entries_sorted(vocab_path)?;
+        let dict = Set::from_iter(entries)?;
+        Ok(Self { dict })
     }
 
     /// Finds all the words that end with the provided suffix
@@ -9,6 +9,7 @@ pub mod util;
 pub mod vocab;
 mod gaddag;
 mod word_search;
+mod zobrist;
 mod constraint;
 
 const BOARD_SIZE: usize = 15;
@@ -1,23 +1,27 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use fst::Set;
-use rand::prelude::SliceRandom;
+use ndarray_rand::rand_distr::{Distribution, WeightedIndex};
+use rand::Rng;
 
 use crate::cfr::state::{Game, GameState};
+use crate::error::{Error, Result};
 use crate::scrabble::{util, BOARD_SIZE};
 
 use super::bag::Bag;
-use super::board::{ScrabbleBoard, Tile};
+use super::board::ScrabbleBoard;
 use super::rack::Rack;
-use super::util::{Letter, Move, Position, SquareEffect};
-
-/// Grid of all possible moves the player can make in the current state.
-/// Once a player chooses an action, a random word is sampled from the list of available words
-/// and applied to the board
-///
+use super::util::Move;
+use super::zobrist;
 
 const MAX_LENGTH: usize = 7;
+/// Number of tiles each player holds while the bag has tiles left
+const RACK_SIZE: usize = 7;
 
+/// Grid of all possible moves the player can make in the current state.
+/// Once a player chooses an action, a chance node picks one of the best words of the action
+/// and applies it to the board
+#[derive(Clone)]
 pub struct MoveGrid {
     /// IDs of each move in the master move array
     /// 15x15x5 (maybe add an extra dimension to allow the choice of direction as well)
@@ -62,23 +66,26 @@ impl MoveGrid {
         }
     }
 
-    pub fn get_move(&self, action_id: usize) -> &Move {
-        // If 0 is passed, then return the best move
+    /// Creates a grid without any moves, i.e. while the racks are still being dealt
+    pub fn empty() -> Self {
+        Self {
+            move_ids: vec![vec![vec![Vec::new(); MAX_LENGTH]; BOARD_SIZE]; BOARD_SIZE],
+            moves: Vec::new(),
+            best_move_id: 0,
+        }
+    }
+
+    /// Gets the ids of the moves the action can place. When there are several of them, each one
+    /// is equally likely to be placed
+    pub fn candidates(&self, action_id: usize) -> &[usize] {
+        // If 0 is passed, then the best move is placed
         if action_id == 0 {
-            return &self.moves[self.best_move_id];
+            return std::slice::from_ref(&self.best_move_id);
         }
         let idx = action_id - 1;
 
         let coord = util::index_to_coord(idx, &[BOARD_SIZE, BOARD_SIZE, MAX_LENGTH]);
-
-        let valid_moves = &self.move_ids[coord[0]][coord[1]][coord[2]];
-        if valid_moves.len() == 1 {
-            return &self.moves[valid_moves[0]];
-        }
-        // Pick a random move
-        let mut rng = rand::thread_rng();
-        let selected_move = valid_moves.choose(&mut rng).unwrap();
-        &self.moves[*selected_move]
+        &self.move_ids[coord[0]][coord[1]][coord[2]]
     }
 
     pub fn moves(&self) -> &[Move] {
@@ -104,6 +111,17 @@ impl MoveGrid {
     }
 }
 
+/// Randomness a state is waiting on before the next player can act
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChanceEvent {
+    /// One of the moves the action can place is picked, each being equally likely
+    PickMove(usize),
+    /// Tiles are drawn from the bag into the rack of the player one at a time, until the
+    /// remaining number of tiles have been drawn or the bag runs out
+    DrawTiles { player: usize, remaining: usize },
+}
+
+#[derive(Clone)]
 pub struct ScrabbleState {
     /// Current tile bag
     pub bag: Bag,
@@ -120,74 +138,99 @@ pub struct ScrabbleState {
     /// Current player active move grid,
     pub curr_move_grid: MoveGrid,
     /// Pointer to the vocabulary to avoid excessive and expensive copies
-    pub vocab: Rc<Set<Vec<u8>>>,
+    pub vocab: Arc<Set<Vec<u8>>>,
+    /// Zobrist hash of the letters on the board, updated as moves are placed
+    pub board_hash: u128,
+    /// Randomness the state is waiting on, if it is a chance node
+    pub chance: Option<ChanceEvent>,
 }
 
 impl ScrabbleState {
+    /// Places the move for the current player, or passes if there is none. The returned state
+    /// is a chance node while the player's rack is being replenished
     pub fn next_state_with_move(&self, selected_move: Option<&Move>) -> ScrabbleState {
-        let mut next_bag = self.bag.clone();
-        let mut next_board = self.board.clone();
-        let mut next_racks = self.player_racks.clone();
-        let mut next_scores = self.player_scores.clone();
-        let mut next_player_active = self.player_active.clone();
+        let mut next = self.clone();
+        let mut n_used = 0;
         if let Some(selected_move) = selected_move {
+            next.board_hash ^= zobrist::move_hash(&next.board, selected_move);
             // Place the word
             let used_letters =
-                next_board.place_word(&selected_move.word, selected_move.pos, selected_move.dir);
+                next.board.place_word(&selected_move.word, selected_move.pos, selected_move.dir);
 
             // Remove all the letters used to place the previous word
             for l in used_letters.iter() {
-                next_racks[self.curr_player].remove_inplace(*l);
-            }
-            // Replenish that same player's rack with new letters
-            for l in next_bag.draw_tiles(used_letters.len()) {
-                next_racks[self.curr_player].add_inplace(l);
+                next.player_racks[self.curr_player].remove_inplace(*l);
             }
             // Add to the current player's score
-            next_scores[self.curr_player] += selected_move.score;
+            next.player_scores[self.curr_player] += selected_move.score;
+            n_used = used_letters.len();
         }
+        // Replenish that same player's rack with new letters
+        next.draw_tiles(self.curr_player, n_used);
+        next
+    }
 
+    /// Samples chance outcomes with the provided random number generator until a player has to
+    /// act or the game is over
+    pub fn sample_chance<R: Rng>(&self, rng: &mut R) -> ScrabbleState {
+        let mut state = self.clone();
+        while state.is_chance() {
+            let outcomes = state.chance_outcomes();
+            let dist = WeightedIndex::new(outcomes.iter().map(|(_, prob)| *prob)).unwrap();
+            state = state.apply_chance_outcome(outcomes[dist.sample(rng)].0);
+        }
+        state
+    }
+
+    /// Waits on the player drawing the provided number of tiles, and moves on once they have
+    /// been drawn
+    fn draw_tiles(&mut self, player: usize, remaining: usize) {
+        if remaining > 0 && !self.bag.is_empty() {
+            self.chance = Some(ChanceEvent::DrawTiles { player, remaining });
+        } else if player != self.curr_player {
+            // Racks are dealt in player order, ending with the current player
+            self.draw_tiles(player + 1, RACK_SIZE);
+        } else {
+            self.chance = None;
+            self.advance_turn();
+        }
+    }
+
+    /// Hands the turn to the next player that can make a move, marking the players that cannot
+    /// as inactive
+    fn advance_turn(&mut self) {
         // Find the next available player until we run out of space
         let mut next_player = (self.curr_player + 1) % self.player_racks.len();
         let mut next_movegrid;
-        let mut inactive_players = next_player_active.iter().filter(|&x| !x == false).count();
+        let mut inactive_players = self.player_active.iter().filter(|&x| !x == false).count();
         loop {
             next_movegrid = MoveGrid::build(
-                &next_bag,
-                &next_board,
+                &self.bag,
+                &self.board,
                 self.vocab.as_ref(),
-                &next_racks[next_player],
+                &self.player_racks[next_player],
             );
 
             if next_movegrid.moves.is_empty() {
-                next_player_active[next_player] = false;
+                self.player_active[next_player] = false;
                 inactive_players += 1;
                 next_player = (next_player + 1) % self.player_racks.len();
             } else {
                 break;
             }
-            if inactive_players >= next_player_active.len() {
+            if inactive_players >= self.player_active.len() {
                 break;
             }
         }
 
-        next_player_active[next_player] = next_movegrid.moves.len() > 0;
-
-        ScrabbleState {
-            bag: next_bag,
-            board: next_board,
-            curr_move_grid: next_movegrid,
-            curr_player: next_player,
-            player_racks: next_racks,
-            player_scores: next_scores,
-            player_active: next_player_active,
-            vocab: self.vocab.clone(),
-        }
+        self.player_active[next_player] = next_movegrid.moves.len() > 0;
+        self.curr_player = next_player;
+        self.curr_move_grid = next_movegrid;
     }
 }
 
 impl GameState for ScrabbleState {
-    type Key = String;
+    type Key = u128;
 
     fn active_player(&self) -> usize {
         self.curr_player
@@ -200,44 +243,38 @@ impl GameState for ScrabbleState {
     }
 
     fn state_key(&self) -> Self::Key {
-        let mut key = String::new();
-        key.push_str(format!("{}", self.curr_player).as_str());
-        /*let mut placed_words = self.board.placements.iter().map(|x| x.word.as_str()).collect::<Vec<_>>();
-        placed_words.sort_unstable();
-        for p in placed_words {
-            key.push_str(p);
-        }*/
-        for row in 0..BOARD_SIZE {
-            for col in 0..BOARD_SIZE {
-                let pos = Position { row, col };
-                match self.board[pos] {
-                    Tile::Empty => key.push(' '),
-                    Tile::Letter(letter) => match letter {
-                        Letter::Letter(l) => key.push(l),
-                        Letter::Blank => key.push('-'),
-                    },
-                    Tile::Special(effect) => match effect {
-                        SquareEffect::Center => key.push('C'),
-                        SquareEffect::DoubleLetter => key.push_str("DL"),
-                        SquareEffect::DoubleWord => key.push_str("DW"),
-                        SquareEffect::TripleLetter => key.push_str("TL"),
-                        SquareEffect::TripleWord => key.push_str("TW"),
-                    },
-                }
-            }
+        // The full state also includes the rack of every player
+        let mut key = self.board_hash ^ zobrist::player_hash(self.curr_player);
+        for (p, rack) in self.player_racks.iter().enumerate() {
+            key ^= zobrist::rack_hash(p, rack);
         }
         key
     }
 
+    fn information_set_key(&self, player: usize) -> Self::Key {
+        // A player can only observe the board and their own rack
+        self.board_hash
+            ^ zobrist::player_hash(self.curr_player)
+            ^ zobrist::rack_hash(player, &self.player_racks[player])
+    }
+
     fn next_state(&self, action: usize) -> Option<Self> {
         // We can only do this if we have available moves
-        let selected_move = if self.curr_move_grid.moves.len() > 0 {
-            Some(self.curr_move_grid.get_move(action))
-        } else {
-            None
-        };
-        let next_state = self.next_state_with_move(selected_move);
-        Some(next_state)
+        if self.curr_move_grid.moves.is_empty() {
+            return Some(self.next_state_with_move(None));
+        }
+        match self.curr_move_grid.candidates(action) {
+            [] => None,
+            [move_id] => {
+                Some(self.next_state_with_move(Some(&self.curr_move_grid.moves[*move_id])))
+            }
+            // A chance node decides which of the equally good moves is placed
+            _ => {
+                let mut next_state = self.clone();
+                next_state.chance = Some(ChanceEvent::PickMove(action));
+                Some(next_state)
+            }
+        }
     }
 
     fn is_terminal(&self) -> bool {
@@ -260,6 +297,39 @@ impl GameState for ScrabbleState {
         false
     }
 
+    fn is_chance(&self) -> bool {
+        self.chance.is_some()
+    }
+
+    fn chance_outcomes(&self) -> Vec<(usize, f32)> {
+        match self.chance {
+            Some(ChanceEvent::PickMove(action)) => {
+                let n_moves = self.curr_move_grid.candidates(action).len();
+                (0..n_moves).map(|i| (i, 1.0 / n_moves as f32)).collect()
+            }
+            // Each tile in the bag is equally likely to be drawn
+            Some(ChanceEvent::DrawTiles { .. }) => self.bag.tile_probabilities(),
+            None => Vec::new(),
+        }
+    }
+
+    fn apply_chance_outcome(&self, outcome: usize) -> Self {
+        match self.chance {
+            Some(ChanceEvent::PickMove(action)) => {
+                let move_id = self.curr_move_grid.candidates(action)[outcome];
+                self.next_state_with_move(Some(&self.curr_move_grid.moves[move_id]))
+            }
+            Some(ChanceEvent::DrawTiles { player, remaining }) => {
+                let mut next_state = self.clone();
+                let letter = next_state.bag.take_tile(outcome);
+                next_state.player_racks[player].add_inplace(letter);
+                next_state.draw_tiles(player, remaining - 1);
+                next_state
+            }
+            None => unreachable!("Chance outcome applied to a state that is not a chance node"),
+        }
+    }
+
     fn get_reward(&self, player: usize) -> f32 {
         self.player_scores[player] as f32
     }
@@ -271,19 +341,26 @@ pub struct ScrabbleGame {
     /// Number of valid actions
     n_actions: usize,
     /// Vocabulary tied to the game
-    vocab: Rc<Set<Vec<u8>>>,
+    vocab: Arc<Set<Vec<u8>>>,
     /// Initial board (to stop multiple IO calls)
     board: ScrabbleBoard,
 }
 
 impl ScrabbleGame {
-    pub fn new(n_players: usize, vocab: Rc<Set<Vec<u8>>>) -> Self {
-        Self {
+    pub fn new(n_players: usize, vocab: Arc<Set<Vec<u8>>>) -> Result<Self> {
+        if n_players == 0 || n_players > zobrist::MAX_PLAYERS {
+            return Err(Error::Config(format!(
+                "Scrabble supports 1 to {} players but {} were requested",
+                zobrist::MAX_PLAYERS,
+                n_players
+            )));
+        }
+        Ok(Self {
             n_actions: BOARD_SIZE * BOARD_SIZE * MAX_LENGTH + 1,
             n_players,
             vocab,
-            board: ScrabbleBoard::from_file("empty_board.json"),
-        }
+            board: ScrabbleBoard::from_file("empty_board.json")?,
+        })
     }
 }
 
@@ -298,33 +375,118 @@ impl Game for ScrabbleGame {
         self.n_actions
     }
 
+    fn name(&self) -> String {
+        "scrabble".to_string()
+    }
+
+    fn params(&self) -> String {
+        format!("n_players={}", self.n_players)
+    }
+
     fn start(&self) -> Self::State {
-        let mut racks = vec![];
-        let scores = vec![0; self.n_players];
-        let mut bag = Bag::default();
-
-        for _ in 0..self.n_players {
-            let mut rack = Rack::empty();
-            for l in bag.draw_tiles(7) {
-                rack.add_inplace(l);
-            }
-            racks.push(rack);
-        }
-        // Compute the initial move grid for the first player
         let board = self.board.clone();
-        let move_grid = MoveGrid::build(&bag, &board, self.vocab.as_ref(), &racks[0]);
-
-        ScrabbleState {
-            bag,
-            curr_move_grid: move_grid,
-            curr_player: 0,
-            player_racks: racks,
-            player_scores: scores,
+        let mut state = ScrabbleState {
+            bag: Bag::new(),
+            curr_move_grid: MoveGrid::empty(),
+            // The deal ends with the last player, so the first turn goes to the first player
+            curr_player: self.n_players - 1,
+            player_racks: vec![Rack::empty(); self.n_players],
+            player_scores: vec![0; self.n_players],
             player_active: vec![true; self.n_players],
+            board_hash: zobrist::board_hash(&board),
             board,
             vocab: self.vocab.clone(),
-        }
+            chance: None,
+        };
+        // Every rack is dealt through chance nodes before the first turn
+        state.draw_tiles(0, RACK_SIZE);
+        state
     }
 
     fn reset(&mut self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use fst::Set;
+
+    use crate::cfr::state::{Game, GameState};
+    use crate::error::Error;
+    use crate::scrabble::util::Letter;
+
+    use super::{ChanceEvent, ScrabbleGame, ScrabbleState};
+
+    /// Index of the letter in the alphabet of the bag
+    fn letter_id(c: char) -> usize {
+        c as usize - 'A' as usize
+    }
+
+    /// Draws the provided letters through the chance nodes of the state
+    fn draw(mut state: ScrabbleState, letters: &str) -> ScrabbleState {
+        for c in letters.chars() {
+            assert!(matches!(state.chance, Some(ChanceEvent::DrawTiles { .. })));
+            state = state.apply_chance_outcome(letter_id(c));
+        }
+        state
+    }
+
+    #[test]
+    fn test_rejects_unsupported_player_counts() {
+        let vocab = Arc::new(Set::from_iter(vec!["CAT"]).unwrap());
+        for n_players in [0, 5] {
+            let game = ScrabbleGame::new(n_players, vocab.clone());
+            assert!(matches!(game, Err(Error::Config(_))));
+        }
+    }
+
+    #[test]
+    fn test_tiles_and_tied_moves_are_chance_outcomes() {
+        let vocab = Arc::new(Set::from_iter(vec!["AT", "TA"]).unwrap());
+        let game = ScrabbleGame::new(2, vocab).unwrap();
+
+        // Racks are dealt one tile at a time, weighted by the number of each letter in the bag
+        let state = game.start();
+        assert!(state.is_chance());
+        let outcomes = state.chance_outcomes();
+        let total = outcomes.iter().map(|(_, p)| p).sum::<f32>();
+        assert!((total - 1.0).abs() < 1e-5);
+        assert!(outcomes.contains(&(letter_id('E'), 0.12)));
+        let state = draw(state, "AAAATTT");
+        let state = draw(state, "AATTEEE");
+        assert!(!state.is_chance());
+        assert_eq!(state.active_player(), 0);
+        let rack: Vec<Letter> = "AAAATTT".chars().map(|c| c.into()).collect();
+        assert_eq!(state.player_racks[0].get_letters(), rack);
+
+        // Moves that tie within an action are picked by a chance node rather than the transition
+        let action = state
+            .valid_actions()
+            .into_iter()
+            .find(|&a| state.curr_move_grid.candidates(a).len() > 1)
+            .unwrap();
+        let picked = state.next_state(action).unwrap();
+        assert!(picked.is_chance());
+        let n_moves = state.curr_move_grid.candidates(action).len();
+        for (i, (outcome, prob)) in picked.chance_outcomes().into_iter().enumerate() {
+            assert_eq!(outcome, i);
+            assert_eq!(prob, 1.0 / n_moves as f32);
+        }
+
+        // The player then draws the tiles they used, before the turn is handed on
+        let placed = picked.apply_chance_outcome(0);
+        assert_eq!(
+            placed.chance,
+            Some(ChanceEvent::DrawTiles {
+                player: 0,
+                remaining: 2
+            })
+        );
+        assert!(placed.chance_outcomes().contains(&(letter_id('A'), 3.0 / 86.0)));
+        let next = draw(placed, "EE");
+        assert!(!next.is_chance());
+        assert_eq!(next.active_player(), 1);
+        assert_eq!(next.player_racks[0].n_total, 7);
+    }
+}
@@ -29,6 +29,8 @@ use gtk::{
     TreeViewExt,
     WindowType,
 };
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use relm::{connect, timeout, Relm, Update, Widget};
 use relm_derive::Msg;
 
@@ -61,6 +63,8 @@ pub struct ScrabbleUI {
 
     /// Agent the user will play against
     agent: ScrabbleAgent,
+    /// Random number generator used to draw tiles and pick between moves of the same action
+    rng: StdRng,
 
     // Internal variables to the view state
     relm_window: gtk::Window,
@@ -87,11 +91,18 @@ impl ScrabbleUI {
         move_options: TreeView,
         move_store: ListStore,
     ) -> Self {
+        let mut rng = StdRng::seed_from_u64(rand::random());
         Self {
-            agent: ScrabbleAgent::new(Default::default()),//ScrabbleAgent::from_file("./strategies/scrabble.ckpt"),
+            agent: ScrabbleAgent::from_strategy_file("./strategies/scrabble.strat")
+                .unwrap_or_else(|e| {
+                    println!("Failed to load the agent strategy: {}", e);
+                    ScrabbleAgent::new(Default::default())
+                }),
             board,
             relm_window: parent,
-            state: initial_state,
+            // Deal the racks before the first turn
+            state: initial_state.sample_chance(&mut rng),
+            rng,
             selected_cell: None,
             relm,
             rack,
@@ -285,12 +296,18 @@ impl ScrabbleUI {
         // Run the player's move
         let target_move = &self.move_data[move_id as usize];
 
-        let next_state = self.state.next_state_with_move(Some(target_move));
+        let next_state = self
+            .state
+            .next_state_with_move(Some(target_move))
+            .sample_chance(&mut self.rng);
 
         // Now run the AI's move
         println!("Getting agent move");
         let ai_move = self.agent.get_action(&next_state);
-        let next_state = next_state.next_state(ai_move).unwrap();
+        let next_state = next_state
+            .next_state(ai_move)
+            .unwrap()
+            .sample_chance(&mut self.rng);
         self.state = next_state;
 
         self.render_scrabble_board();
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use fst::Automaton;
 
@@ -16,7 +16,7 @@ use super::util::Letter;
 #[derive(Debug, Clone, PartialEq)]
 pub enum BlankAssignmentList {
     Empty,
-    Elem((char, usize), Rc<BlankAssignmentList>),
+    Elem((char, usize), Arc<BlankAssignmentList>),
 }
 
 #[derive(Debug, Clone)]
@@ -120,7 +120,7 @@ impl<'a> Automaton for WordSearcher<'a> {
                                 blank_assignments: if let Some(assig) = blank_assignment {
                                     BlankAssignmentList::Elem(
                                         (assig, state.position),
-                                        Rc::new(state.blank_assignments.clone()),
+                                        Arc::new(state.blank_assignments.clone()),
                                     )
                                 } else {
                                     state.blank_assignments.clone()
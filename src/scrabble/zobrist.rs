@@ -0,0 +1,174 @@
+use std::sync::OnceLock;
+
+use super::board::{ScrabbleBoard, Tile};
+use super::rack::Rack;
+use super::util::{Letter, Move, Position};
+use super::BOARD_SIZE;
+
+/// Maximum number of players the hashes support
+pub const MAX_PLAYERS: usize = 4;
+/// Number of distinct tiles: the 26 letters and the blank
+const NUM_LETTERS: usize = 27;
+/// Maximum number of copies of a single tile a rack can hold
+const MAX_RACK_COUNT: usize = 7;
+/// Seed of the hash values. Keys are saved in checkpoints, so this must never change
+const SEED: u64 = 0x5EED_5C4A_BB1E_2024;
+
+/// Random values that are XORed together to build the hash of a state (Zobrist hashing). Since
+/// XOR is its own inverse, placing a tile only requires XORing in the value of that tile rather
+/// than rehashing the whole board
+struct ZobristTable {
+    /// Value of each tile on each square
+    tiles: Vec<u128>,
+    /// Value of each player being the active player
+    players: [u128; MAX_PLAYERS],
+    /// Value of the k-th copy of each tile in the rack of each player
+    racks: Vec<u128>,
+}
+
+impl ZobristTable {
+    fn new() -> Self {
+        let mut state = SEED;
+        let mut next = || (splitmix64(&mut state) as u128) << 64 | splitmix64(&mut state) as u128;
+        Self {
+            tiles: (0..BOARD_SIZE * BOARD_SIZE * NUM_LETTERS)
+                .map(|_| next())
+                .collect(),
+            players: [next(), next(), next(), next()],
+            racks: (0..MAX_PLAYERS * NUM_LETTERS * MAX_RACK_COUNT)
+                .map(|_| next())
+                .collect(),
+        }
+    }
+}
+
+/// Generates the next value of a SplitMix64 sequence. This is used rather than a generator from
+/// `rand` so the hash values can never change between versions of the crate
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn table() -> &'static ZobristTable {
+    static TABLE: OnceLock<ZobristTable> = OnceLock::new();
+    TABLE.get_or_init(ZobristTable::new)
+}
+
+fn letter_index(letter: Letter) -> usize {
+    match letter {
+        Letter::Letter(c) => {
+            debug_assert!(c.is_ascii_uppercase(), "Unexpected letter {}", c);
+            (c as u8 - b'A') as usize
+        }
+        Letter::Blank => NUM_LETTERS - 1,
+    }
+}
+
+/// Hash of a letter placed on the provided square
+pub fn tile_hash(pos: Position, letter: Letter) -> u128 {
+    let square = pos.row * BOARD_SIZE + pos.col;
+    table().tiles[square * NUM_LETTERS + letter_index(letter)]
+}
+
+/// Hash of the provided player being the active player
+pub fn player_hash(player: usize) -> u128 {
+    table().players[player]
+}
+
+/// Hash of the tiles in the rack of the provided player. The order of the tiles does not matter
+pub fn rack_hash(player: usize, rack: &Rack) -> u128 {
+    let table = table();
+    let mut hash = 0;
+    let counts = (b'A'..=b'Z')
+        .map(|c| (Letter::Letter(c as char), rack.letters[c as usize]))
+        .chain(std::iter::once((Letter::Blank, rack.n_blanks)));
+    for (letter, count) in counts {
+        let offset = (player * NUM_LETTERS + letter_index(letter)) * MAX_RACK_COUNT;
+        for k in 0..(count as usize).min(MAX_RACK_COUNT) {
+            hash ^= table.racks[offset + k];
+        }
+    }
+    hash
+}
+
+/// Hash of every letter on the board
+pub fn board_hash(board: &ScrabbleBoard) -> u128 {
+    let mut hash = 0;
+    for row in 0..BOARD_SIZE {
+        for col in 0..BOARD_SIZE {
+            let pos = Position { row, col };
+            if let Tile::Letter(letter) = board[pos] {
+                hash ^= tile_hash(pos, letter);
+            }
+        }
+    }
+    hash
+}
+
+/// Hash of the letters the move adds to the board. XORing this into the hash of the board before
+/// the move is placed gives the hash of the board after it is placed
+pub fn move_hash(board: &ScrabbleBoard, m: &Move) -> u128 {
+    let mut hash = 0;
+    let mut pos = m.pos;
+    for c in m.word.chars() {
+        // Letters that are already on the board are part of the existing hash
+        if !matches!(board[pos], Tile::Letter(_)) {
+            hash ^= tile_hash(pos, Letter::Letter(c.to_ascii_uppercase()));
+        }
+        pos[m.dir] += 1;
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scrabble::board::ScrabbleBoard;
+    use crate::scrabble::rack::Rack;
+    use crate::scrabble::util::{Direction, Letter, Move, Position};
+
+    use super::{board_hash, move_hash, rack_hash};
+
+    fn word_move(word: &str, row: usize, col: usize, dir: Direction) -> Move {
+        Move {
+            word: word.to_string(),
+            pos: Position { row, col },
+            dir,
+            score: 0,
+        }
+    }
+
+    #[test]
+    fn test_incremental_board_hash() {
+        let mut board = ScrabbleBoard::empty();
+        let mut hash = board_hash(&board);
+        // The second word crosses the A that is already on the board
+        for m in [
+            word_move("CAT", 7, 6, Direction::Across),
+            word_move("bAd", 6, 7, Direction::Down),
+        ] {
+            hash ^= move_hash(&board, &m);
+            board.place_word(&m.word, m.pos, m.dir);
+            assert_eq!(hash, board_hash(&board));
+        }
+        assert_ne!(hash, board_hash(&ScrabbleBoard::empty()));
+    }
+
+    #[test]
+    fn test_rack_hash_ignores_order() {
+        let mut a = Rack::empty();
+        let mut b = Rack::empty();
+        for l in ['E', 'E', 'Q'] {
+            a.add_inplace(Letter::Letter(l));
+        }
+        b.add_inplace(Letter::Letter('Q'));
+        b.add_inplace(Letter::Letter('E'));
+        assert_ne!(rack_hash(0, &a), rack_hash(0, &b));
+
+        b.add_inplace(Letter::Letter('E'));
+        assert_eq!(rack_hash(0, &a), rack_hash(0, &b));
+        assert_ne!(rack_hash(0, &a), rack_hash(1, &b));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::cfr::state::{Game, GameState};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TicTacToeState {
     board: Vec<Vec<usize>>,
     curr_player: usize,
@@ -97,7 +97,7 @@ impl TicTacToeState {
 }
 
 impl GameState for TicTacToeState {
-    type Key = String;
+    type Key = u64;
 
     fn active_player(&self) -> usize {
         self.curr_player
@@ -118,18 +118,14 @@ impl GameState for TicTacToeState {
     }
 
     fn state_key(&self) -> Self::Key {
-        let mut key = String::new();
-        key.push_str(format!("{}", self.curr_player).as_str());
+        // Each cell is a base 3 digit (empty, X or O), followed by a bit for the active player
+        let mut key = 0;
         for i in 0..self.board.len() {
             for j in 0..self.board[i].len() {
-                key.push_str(match self.board[i][j] {
-                    1 => "X",
-                    2 => "O",
-                    _ => "-",
-                });
+                key = key * 3 + self.board[i][j] as u64;
             }
         }
-        key
+        key * 2 + self.curr_player as u64
     }
 
     fn next_state(&self, action: usize) -> Option<Self> {
@@ -180,6 +176,8 @@ pub struct TicTacToe {
 
 impl TicTacToe {
     pub fn new(board_dim: usize) -> Self {
+        // Keys pack every cell of the board into a u64
+        assert!(board_dim <= 6, "Boards larger than 6x6 are not supported");
         Self { board_dim }
     }
 }
@@ -195,6 +193,14 @@ impl Game for TicTacToe {
         self.board_dim * self.board_dim
     }
 
+    fn name(&self) -> String {
+        "tictactoe".to_string()
+    }
+
+    fn params(&self) -> String {
+        format!("board_dim={}", self.board_dim)
+    }
+
     fn start(&self) -> Self::State {
         let board = (0..self.board_dim)
             .map(|_| {
@@ -290,4 +296,23 @@ mod tests {
         assert_ne!(valid_actions.len(), next_valid_actions.len());
         assert_ne!(state.state_key(), next_state.state_key());
     }
+
+    #[test]
+    fn test_keys_include_active_player() {
+        let board = vec![vec![1, 2, 0], vec![0, 0, 0], vec![0, 0, 0]];
+        let state = TicTacToeState {
+            curr_player: 0,
+            board: board.clone(),
+        };
+        let other_player = TicTacToeState {
+            curr_player: 1,
+            board,
+        };
+        let swapped = TicTacToeState {
+            curr_player: 0,
+            board: vec![vec![2, 1, 0], vec![0, 0, 0], vec![0, 0, 0]],
+        };
+        assert_ne!(state.state_key(), other_player.state_key());
+        assert_ne!(state.state_key(), swapped.state_key());
+    }
 }
@@ -1,33 +1,51 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 
 use bincode;
 use bincode::Options;
 
-pub fn serialize_bytes<T: Serialize>(data: &T) -> Vec<u8> {
-    bincode::serialize(data).unwrap()
+use crate::error::Result;
+
+pub fn serialize_bytes<T: Serialize>(data: &T) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(data)?)
 }
-pub fn deserialize_bytes<T: DeserializeOwned>(bytes: &[u8]) -> T {
-    bincode::deserialize(bytes).unwrap()
+pub fn deserialize_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    Ok(bincode::deserialize(bytes)?)
 }
 
-pub fn save_to_disk<T: Serialize, TPath: AsRef<Path>>(data: &T, path: TPath) {
+pub fn save_to_disk<T: Serialize, TPath: AsRef<Path>>(data: &T, path: TPath) -> Result<()> {
     let options = bincode::DefaultOptions::new();
     let options = options.with_no_limit();
     // Write all bytes to the target file
-    let file = File::create(path).unwrap();
-    let writer = BufWriter::new(file);
-    options.serialize_into(writer, data).unwrap();
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    options.serialize_into(&mut writer, data)?;
+    writer.flush()?;
+    Ok(())
 }
 
-pub fn load_from_disk<T: DeserializeOwned, TPath: AsRef<Path>>(path: TPath) -> T {
+pub fn load_from_disk<T: DeserializeOwned, TPath: AsRef<Path>>(path: TPath) -> Result<T> {
     // Open the file and read all bytes
-    let file = File::open(path).unwrap();
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
     let options = bincode::DefaultOptions::new();
     let options = options.with_no_limit();
-    options.deserialize_from(reader).unwrap()
+    Ok(options.deserialize_from(reader)?)
+}
+
+/// Serializes the data into the writer. Several values can be written back to back
+pub fn serialize_into<W: Write, T: Serialize>(writer: W, data: &T) -> bincode::Result<()> {
+    let options = bincode::DefaultOptions::new();
+    let options = options.with_no_limit();
+    options.serialize_into(writer, data)
+}
+
+/// Deserializes the next value from the reader
+pub fn deserialize_from<R: Read, T: DeserializeOwned>(reader: R) -> bincode::Result<T> {
+    let options = bincode::DefaultOptions::new();
+    let options = options.with_no_limit();
+    options.deserialize_from(reader)
 }
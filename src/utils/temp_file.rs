@@ -0,0 +1,45 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Temporary file that is written in full before it is renamed over its final path, so readers
+/// never see a partially written file. The temporary file is removed when the guard is dropped
+/// unless it was persisted, so a failed write never leaves it behind
+pub struct TempFile {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl TempFile {
+    /// Reserves `<path>.tmp` as the temporary file of the provided path
+    pub fn for_path<P: AsRef<Path>>(path: P) -> Self {
+        let mut tmp_path = OsString::from(path.as_ref().as_os_str());
+        tmp_path.push(".tmp");
+        Self {
+            path: PathBuf::from(tmp_path),
+            persisted: false,
+        }
+    }
+
+    /// Path of the temporary file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Renames the temporary file over the provided path
+    pub fn persist<P: AsRef<Path>>(mut self, path: P) -> io::Result<()> {
+        fs::rename(&self.path, path)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            // The file may never have been created if the write failed early
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}